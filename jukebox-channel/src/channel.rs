@@ -1,17 +1,27 @@
 use core::fmt;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     fmt::{Debug, Formatter},
     ops::{AddAssign, Sub},
-    time::Duration,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
-use jukebox_decoder::{Frame, Stream};
-use jukebox_playlist::Playlist;
-use tokio::time::Instant;
-use tracing::{info, trace};
+use jukebox_decoder::{Empty, Format, Frame, Stream};
+use jukebox_playlist::{LibraryId, Playlist};
+use tokio::{
+    sync::{oneshot, watch},
+    task::JoinHandle,
+    time::Instant,
+};
+use tracing::{info, trace, warn};
 
-use crate::StreamWeak;
+use crate::{
+    config::{ChannelConfig, ChannelMode, IcecastConfig, Plays, StreamConfig, Titles},
+    icecast::Source,
+    segment::{Segmenter, Segments},
+    stream::{Broadcast, Stream as Listener},
+};
 
 #[derive(Clone)]
 struct ChannelTime {
@@ -19,26 +29,78 @@ struct ChannelTime {
     frames: HashMap<usize, usize>,
 }
 
-#[derive(Default)]
 pub struct Channel<T>
 where
     T: Playlist,
 {
-    playlist: T,
+    /// Away while a task prefetches the next track
+    playlist: Option<T>,
+    prefetching: Option<JoinHandle<T>>,
+    /// Song of the current decoder
+    current: Option<LibraryId>,
 
     data: Option<Box<dyn Stream>>,
+    prefetched: bool,
+    /// Played songs, the current one at the back
+    history: VecDeque<LibraryId>,
     time: ChannelTime,
     start_time: ChannelTime,
     pause_time: Option<Instant>,
+    /// Frames were skipped while nobody listened, the segments don't follow
+    skipped: bool,
+    mode: ChannelMode,
+    format: Option<Format>,
+    burst: Duration,
+    stream: StreamConfig,
 
-    streams: Vec<StreamWeak>,
+    output: Broadcast,
+    /// When the channel started, for the status of its mount
+    started: SystemTime,
+    /// Highest number of listeners connected at once
+    peak: usize,
+    hls: Option<Segmenter>,
+    dash: Option<Segmenter>,
+    icecast: Vec<IcecastConfig>,
+    sources: Vec<Source>,
+
+    titles: Option<Titles>,
+    /// Title of the current song, once resolved
+    title: watch::Sender<Option<String>>,
+    /// Song whose title is looked up, the lookups of older songs are dropped
+    titled: Arc<Mutex<Option<LibraryId>>>,
+    plays: Option<Plays>,
 }
 
 pub enum ChannelAction {
-    Register(StreamWeak),
+    Register(oneshot::Sender<Listener>),
     Next,
     Previous,
     Rewind,
+    Listeners(oneshot::Sender<usize>),
+    History(oneshot::Sender<Vec<LibraryId>>),
+    Status(oneshot::Sender<ChannelStatus>),
+    Title(oneshot::Sender<watch::Receiver<Option<String>>>),
+    Hls(oneshot::Sender<Option<Segments>>),
+    Dash(oneshot::Sender<Option<Segments>>),
+}
+
+/// Snapshot of what a channel is playing.
+#[derive(Debug, Clone)]
+pub struct ChannelStatus {
+    pub listeners: usize,
+    pub format: Option<Format>,
+    pub current: Option<LibraryId>,
+    /// Position in the current song
+    pub position: Duration,
+    /// Title of the current song, once resolved
+    pub title: Option<String>,
+    /// Average bitrate of the buffered audio, in kbit/s
+    pub bitrate: Option<u32>,
+    pub started: SystemTime,
+    /// Highest number of listeners connected at once
+    pub peak: usize,
+    /// Frames lost by slow listeners
+    pub dropped: u64,
 }
 
 impl Debug for ChannelAction {
@@ -48,6 +110,12 @@ impl Debug for ChannelAction {
             ChannelAction::Next => write!(f, "Next"),
             ChannelAction::Previous => write!(f, "Previous"),
             ChannelAction::Rewind => write!(f, "Rewind"),
+            ChannelAction::Listeners(_) => write!(f, "Listeners"),
+            ChannelAction::History(_) => write!(f, "History"),
+            ChannelAction::Status(_) => write!(f, "Status"),
+            ChannelAction::Title(_) => write!(f, "Title"),
+            ChannelAction::Hls(_) => write!(f, "Hls"),
+            ChannelAction::Dash(_) => write!(f, "Dash"),
         }
     }
 }
@@ -77,11 +145,15 @@ impl ChannelTime {
     fn resync(&mut self, episilon: Duration) {
         self.start += episilon;
     }
+
+    fn add(&mut self, nb_samples: usize, sample_rate: usize) {
+        *self.frames.entry(sample_rate).or_default() += nb_samples;
+    }
 }
 
 impl AddAssign<&Frame> for ChannelTime {
     fn add_assign(&mut self, rhs: &Frame) {
-        *self.frames.entry(rhs.sample_rate).or_default() += rhs.nb_samples;
+        self.add(rhs.nb_samples, rhs.sample_rate);
     }
 }
 
@@ -95,96 +167,576 @@ impl Sub<&ChannelTime> for &ChannelTime {
 
 impl<T> Channel<T>
 where
-    T: Playlist,
+    T: Playlist + 'static,
 {
-    pub(crate) fn new(playlist: T) -> Self {
+    const HISTORY_SIZE: usize = 100;
+
+    pub(crate) fn new(playlist: T, config: ChannelConfig) -> Self {
         let now = ChannelTime::default();
+        let output = Broadcast::new(config.stream);
+        let title = watch::channel(None).0;
+        let sources = Self::sources(&config.icecast, config.format, &output, &title);
         Self {
-            playlist,
+            playlist: Some(playlist),
+            prefetching: None,
+            current: None,
 
-            pause_time: Some(now.start),
+            pause_time: match config.mode {
+                ChannelMode::OnDemand => Some(now.start),
+                ChannelMode::Continuous => None,
+            },
+            skipped: false,
+            mode: config.mode,
+            format: config.format,
+            burst: config.burst,
+            stream: config.stream,
             time: now.clone(),
             start_time: now,
             data: Default::default(),
-            streams: Default::default(),
+            prefetched: false,
+            history: Default::default(),
+            output,
+            started: SystemTime::now(),
+            peak: 0,
+            hls: config.hls.map(Segmenter::new),
+            dash: config.dash.map(Segmenter::new),
+            icecast: config.icecast,
+            sources,
+            titles: config.titles,
+            title,
+            titled: Default::default(),
+            plays: config.plays,
         }
     }
 
-    pub(crate) fn register(&mut self, stream: StreamWeak) {
-        self.streams.push(stream)
+    /// Source clients pushing the output to Icecast servers, they aren't
+    /// listeners of the channel but keep it playing.
+    fn sources(
+        icecast: &[IcecastConfig],
+        format: Option<Format>,
+        output: &Broadcast,
+        title: &watch::Sender<Option<String>>,
+    ) -> Vec<Source> {
+        icecast
+            .iter()
+            .map(|config| {
+                Source::spawn(
+                    config.clone(),
+                    format,
+                    output.subscribe_uncounted(),
+                    title.subscribe(),
+                )
+            })
+            .collect()
+    }
+
+    /// Switch to another playlist after the current song. Listeners stay
+    /// connected unless the stream configuration or the format changed,
+    /// segments are kept unless the configuration of their output changed.
+    pub(crate) fn reconfigure(&mut self, playlist: T, config: ChannelConfig) {
+        if let Some(prefetching) = self.prefetching.take() {
+            prefetching.abort();
+        }
+        self.playlist = Some(playlist);
+        self.prefetched = false;
+        self.mode = config.mode;
+        self.burst = config.burst;
+        let restart = config.stream != self.stream || config.format != self.format;
+        if restart {
+            self.stream = config.stream;
+            self.format = config.format;
+            self.output = Broadcast::new(config.stream);
+        }
+        if restart || config.icecast != self.icecast {
+            self.sources = Self::sources(&config.icecast, self.format, &self.output, &self.title);
+            self.icecast = config.icecast;
+        }
+        self.titles = config.titles;
+        self.plays = config.plays;
+        if config.hls != self.hls.as_ref().map(Segmenter::config) {
+            self.hls = config.hls.map(Segmenter::new);
+        }
+        if config.dash != self.dash.as_ref().map(Segmenter::config) {
+            self.dash = config.dash.map(Segmenter::new);
+        }
+    }
+
+    pub(crate) fn register(&mut self, reply: oneshot::Sender<Listener>) {
+        let _ = reply.send(self.output.subscribe(self.burst));
+        self.peak = self.peak.max(self.output.listeners());
     }
 
     pub(crate) async fn run(&mut self, now: Instant) {
-        self.streams = self
-            .streams
-            .iter()
-            .filter(|e| e.active())
-            .cloned()
-            .collect();
+        let listeners = self.output.listeners();
+        let segmented = self
+            .segmenters()
+            .any(|segmenter| segmenter.segments().active(now));
+        // The Icecast servers relay the channel to their own listeners
+        let pushed = !self.sources.is_empty();
 
-        match (&self.pause_time, self.streams.is_empty()) {
+        let skip = match (&self.pause_time, listeners == 0 && !segmented && !pushed) {
+            (None, true) if self.mode == ChannelMode::Continuous => {
+                // Nobody listens but keep up with wall-clock time, without
+                // producing the frames
+                true
+            }
             (None, true) => {
                 // Stop stream
                 self.pause_time = Some(now);
                 return;
             }
-            (Some(_), true) => {
+            (Some(_), true) if self.mode == ChannelMode::OnDemand => {
                 return;
             }
-            (None, false) => {}
-            (Some(pause_time), false) => {
+            (None, false) => false,
+            (Some(pause_time), _) => {
                 // Restart stream
                 let duration = now - *pause_time;
                 self.start_time.resync(duration);
                 self.time.resync(duration);
-                self.pause_time = None
+                self.pause_time = None;
+                self.segmenters().for_each(Segmenter::discontinuity);
+                false
             }
+        };
+        if skip {
+            self.skipped = true;
+        } else if std::mem::take(&mut self.skipped) {
+            // The segments miss the skipped frames
+            self.segmenters().for_each(Segmenter::discontinuity);
         }
 
-        trace!("channel: nb stream {}", self.streams.len());
+        trace!("channel: nb stream {}", listeners);
 
+        let mut empty = false;
         while self.time.now() < now {
-            if self.data.is_none() {
-                self.action(ChannelAction::Next).await;
-            }
-            let decoder = self.data.as_mut().unwrap();
-            match decoder.next() {
-                Some(frame) => {
-                    self.time += &frame;
-                    for stream in self.streams.iter() {
-                        stream.push(frame.as_ref()).await;
+            let played = if skip {
+                match self.skip_frame().await {
+                    Some((nb_samples, sample_rate)) => {
+                        self.time.add(nb_samples, sample_rate);
+                        true
                     }
+                    None => false,
                 }
-                None => {
-                    self.data = None;
+            } else {
+                match self.next_frame().await {
+                    Some(frame) => {
+                        self.time += &frame;
+                        self.output.push(&frame);
+                        let track = self.current;
+                        self.segmenters()
+                            .for_each(|segmenter| segmenter.push(&frame, track));
+                        true
+                    }
+                    None => false,
                 }
+            };
+            match played {
+                true => empty = false,
+                false if empty => {
+                    // The playlist has nothing to play, hold the position
+                    let duration = now - self.time.now();
+                    self.start_time.resync(duration);
+                    self.time.resync(duration);
+                    break;
+                }
+                false => empty = true,
             }
         }
+
+        self.segmenters().for_each(Segmenter::flush);
+
+        if !self.prefetched
+            && let Some(mut playlist) = self.playlist.take()
+        {
+            // Load the next track in its own task, the other channels keep
+            // playing meanwhile
+            self.prefetching = Some(tokio::spawn(async move {
+                playlist.prefetch().await;
+                playlist
+            }));
+            self.prefetched = true;
+        }
         info!("channel: position {:?}", &self.time - &self.start_time);
     }
 
+    /// The playlist, waiting for the prefetch task to hand it back. `None`
+    /// once the task was cancelled, until the manager rebuilds the playlist.
+    async fn playlist(&mut self) -> Option<&mut T> {
+        if let Some(prefetching) = self.prefetching.take() {
+            match prefetching.await {
+                Ok(playlist) => self.playlist = Some(playlist),
+                Err(error) => match error.try_into_panic() {
+                    Ok(panic) => std::panic::resume_unwind(panic),
+                    Err(_) => warn!("channel: prefetch cancelled, the playlist is lost"),
+                },
+            }
+        }
+        self.playlist.as_mut()
+    }
+
+    /// Whether the playlist went away with a cancelled prefetch task.
+    pub(crate) fn lost_playlist(&self) -> bool {
+        self.playlist.is_none() && self.prefetching.is_none()
+    }
+
+    /// Replace a lost playlist.
+    pub(crate) fn restore_playlist(&mut self, playlist: T) {
+        self.playlist = Some(playlist);
+        self.prefetched = false;
+    }
+
+    /// The decoder of the current song, the next one once it ended.
+    async fn decoder(&mut self) -> &mut Box<dyn Stream> {
+        if self.data.is_none() {
+            // The song played to its end
+            self.next_track(false).await;
+        }
+        self.data.as_mut().unwrap()
+    }
+
+    async fn next_frame(&mut self) -> Option<Frame> {
+        let frame = self.decoder().await.next();
+        if frame.is_none() {
+            self.data = None;
+        }
+        frame
+    }
+
+    /// Like `next_frame` without producing the frame, only its number of
+    /// samples and sample rate.
+    async fn skip_frame(&mut self) -> Option<(usize, usize)> {
+        let skipped = self.decoder().await.skip_frame();
+        if skipped.is_none() {
+            self.data = None;
+        }
+        skipped
+    }
+
     pub(crate) async fn action(&mut self, action: ChannelAction) {
-        println!("channel: action {:?}", action);
         info!("channel: action {:?}", action);
         match action {
-            ChannelAction::Register(stream) => self.register(stream),
-            ChannelAction::Next => {
-                let data = self.playlist.next().await;
-                self.update_decoder(data)
-            }
+            ChannelAction::Register(reply) => self.register(reply),
+            ChannelAction::Next => self.next_track(true).await,
             ChannelAction::Previous => {
-                let data = self.playlist.prev().await;
-                self.update_decoder(data)
+                if let Some(playlist) = self.playlist().await {
+                    let data = playlist.prev().await;
+                    self.update_decoder(data);
+                }
             }
             ChannelAction::Rewind => {
-                let data = self.playlist.rewind().await;
-                self.update_decoder(data)
+                if let Some(playlist) = self.playlist().await {
+                    let data = playlist.rewind().await;
+                    self.update_decoder(data);
+                }
+            }
+            ChannelAction::Listeners(reply) => {
+                let _ = reply.send(self.output.listeners());
+            }
+            ChannelAction::History(reply) => {
+                let _ = reply.send(self.history.iter().copied().collect());
+            }
+            ChannelAction::Status(reply) => {
+                let _ = reply.send(ChannelStatus {
+                    listeners: self.output.listeners(),
+                    format: self.format,
+                    current: self.current,
+                    position: &self.time - &self.start_time,
+                    title: self.title.borrow().clone(),
+                    bitrate: self.output.bitrate(),
+                    started: self.started,
+                    peak: self.peak,
+                    dropped: self.output.dropped(),
+                });
+            }
+            ChannelAction::Title(reply) => {
+                let _ = reply.send(self.title.subscribe());
+            }
+            ChannelAction::Hls(reply) => {
+                let _ = reply.send(self.hls.as_ref().map(|hls| hls.segments().clone()));
+            }
+            ChannelAction::Dash(reply) => {
+                let _ = reply.send(self.dash.as_ref().map(|dash| dash.segments().clone()));
             }
         };
     }
 
-    fn update_decoder(&mut self, data: Box<dyn Stream>) {
+    fn segmenters(&mut self) -> impl Iterator<Item = &mut Segmenter> {
+        self.hls.iter_mut().chain(self.dash.iter_mut())
+    }
+
+    /// Switch to the next song of the playlist, `skip` before it ended.
+    async fn next_track(&mut self, skip: bool) {
+        let Some(playlist) = self.playlist().await else {
+            // Nothing to play until the playlist is rebuilt
+            self.data = Some(Box::new(Empty));
+            return;
+        };
+        if skip {
+            playlist.skip();
+        }
+        let data = playlist.next().await;
+        if self.update_decoder(data)
+            && let (Some(id), Some(plays)) = (self.current, &self.plays)
+        {
+            (plays.0)(id).await;
+        }
+    }
+
+    /// Whether the channel streams the new decoder, the songs in another
+    /// format are skipped.
+    fn update_decoder(&mut self, data: Box<dyn Stream>) -> bool {
+        let current = self.playlist.as_ref().and_then(Playlist::current);
+        if let (Some(expected), Some(format)) = (self.format, data.format())
+            && format != expected
+        {
+            // Listeners can't decode a change of format, skip the track
+            warn!(
+                "channel: skip track {:?} in {}, the channel streams {}",
+                current, format.mime, expected.mime
+            );
+            // Don't come back to it when it ends, like a repeated song
+            if let Some(playlist) = self.playlist.as_mut() {
+                playlist.skip();
+            }
+            self.current = None;
+            *self.titled.lock().unwrap() = None;
+            self.data = Some(Box::new(Empty));
+            self.prefetched = false;
+            return false;
+        }
+        self.current = current;
+        *self.titled.lock().unwrap() = current;
         self.data = Some(data);
+        self.prefetched = false;
+        if let Some(id) = current {
+            if let Some(titles) = &self.titles {
+                let title = (titles.0)(id);
+                let sender = self.title.clone();
+                let titled = self.titled.clone();
+                tokio::spawn(async move {
+                    if let Some(title) = title.await {
+                        // Under the lock of the title, a newer lookup can't
+                        // be overwritten
+                        sender.send_if_modified(|current| {
+                            let fresh = *titled.lock().unwrap() == Some(id);
+                            if fresh {
+                                *current = Some(title);
+                            }
+                            fresh
+                        });
+                    }
+                });
+            }
+            self.history.push_back(id);
+            if self.history.len() > Self::HISTORY_SIZE {
+                self.history.pop_front();
+            }
+        }
         self.start_time = self.time.clone();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use jukebox_decoder::Container;
+
+    use super::*;
+
+    const OGG: Format = Format {
+        mime: "audio/ogg",
+        container: Container::Ogg,
+    };
+
+    /// Endless song of a single format.
+    struct Track(Format);
+
+    impl Iterator for Track {
+        type Item = Frame;
+
+        fn next(&mut self) -> Option<Frame> {
+            Some(Frame::new(Bytes::from_static(&[0]), 1152, 44100))
+        }
+    }
+
+    impl Stream for Track {
+        fn format(&self) -> Option<Format> {
+            Some(self.0)
+        }
+    }
+
+    /// Plays its songs in a loop.
+    #[derive(Clone)]
+    struct Tracks {
+        tracks: Vec<(LibraryId, Format)>,
+        position: Option<usize>,
+    }
+
+    impl Tracks {
+        fn new(tracks: &[(LibraryId, Format)]) -> Self {
+            Self {
+                tracks: tracks.to_vec(),
+                position: None,
+            }
+        }
+    }
+
+    impl Playlist for Tracks {
+        async fn next(&mut self) -> Box<dyn Stream> {
+            let position = self.position.map_or(0, |position| position + 1) % self.tracks.len();
+            self.position = Some(position);
+            Box::new(Track(self.tracks[position].1))
+        }
+
+        async fn prev(&mut self) -> Box<dyn Stream> {
+            self.rewind().await
+        }
+
+        async fn rewind(&mut self) -> Box<dyn Stream> {
+            Box::new(Track(self.tracks[self.position.unwrap_or_default()].1))
+        }
+
+        fn current(&self) -> Option<LibraryId> {
+            self.position.map(|position| self.tracks[position].0)
+        }
+    }
+
+    fn plays() -> (Plays, Arc<Mutex<Vec<LibraryId>>>) {
+        let played = Arc::new(Mutex::new(Vec::new()));
+        let plays = {
+            let played = played.clone();
+            Plays(Arc::new(move |id| {
+                played.lock().unwrap().push(id);
+                Box::pin(async {})
+            }))
+        };
+        (plays, played)
+    }
+
+    #[tokio::test]
+    async fn skip_other_format() {
+        let (plays, played) = plays();
+        let config = ChannelConfig {
+            format: Some(Format::MP3),
+            plays: Some(plays),
+            ..Default::default()
+        };
+        let mut channel = Channel::new(Tracks::new(&[(0, OGG), (1, Format::MP3)]), config);
+
+        channel.action(ChannelAction::Next).await;
+        assert_eq!(channel.current, None);
+        assert!(played.lock().unwrap().is_empty());
+        assert!(channel.history.is_empty());
+
+        channel.action(ChannelAction::Next).await;
+        assert_eq!(channel.current, Some(1));
+        assert_eq!(*played.lock().unwrap(), [1]);
+        assert_eq!(channel.history, [1]);
+    }
+
+    #[tokio::test]
+    async fn drop_stale_title() {
+        let titles = Titles(Arc::new(|id| {
+            Box::pin(async move {
+                if id == 0 {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                Some(id.to_string())
+            })
+        }));
+        let config = ChannelConfig {
+            titles: Some(titles),
+            ..Default::default()
+        };
+        let mut channel = Channel::new(Tracks::new(&[(0, Format::MP3), (1, Format::MP3)]), config);
+        let mut title = channel.title.subscribe();
+
+        channel.action(ChannelAction::Next).await;
+        channel.action(ChannelAction::Next).await;
+        title.changed().await.unwrap();
+        assert_eq!(*title.borrow_and_update(), Some("1".to_string()));
+
+        // The lookup of the first song ends after the second one
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!title.has_changed().unwrap());
+        assert_eq!(*channel.title.borrow(), Some("1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn continuous_skips_without_listeners() {
+        let config = ChannelConfig {
+            mode: ChannelMode::Continuous,
+            ..Default::default()
+        };
+        let mut channel = Channel::new(Tracks::new(&[(0, Format::MP3)]), config);
+        let now = channel.time.now() + Duration::from_secs(1);
+
+        channel.run(now).await;
+        assert!(channel.time.now() >= now);
+        assert_eq!(channel.current, Some(0));
+        assert_eq!(channel.output.bitrate(), None);
+
+        let _listener = channel.output.subscribe(Duration::ZERO);
+        channel.run(now + Duration::from_secs(1)).await;
+        assert!(channel.time.now() >= now + Duration::from_secs(1));
+        assert!(channel.output.bitrate().is_some());
+    }
+
+    #[tokio::test]
+    async fn sources_are_not_listeners() {
+        let config = ChannelConfig {
+            icecast: vec![IcecastConfig {
+                address: "127.0.0.1:1".to_string(),
+                mount: "/live".to_string(),
+                user: "source".to_string(),
+                password: "hackme".to_string(),
+                protocol: Default::default(),
+                name: None,
+                description: None,
+                genre: None,
+                public: false,
+            }],
+            ..Default::default()
+        };
+        let mut channel = Channel::new(Tracks::new(&[(0, Format::MP3)]), config);
+        assert_eq!(channel.sources.len(), 1);
+
+        let (reply, status) = oneshot::channel();
+        channel.action(ChannelAction::Status(reply)).await;
+        let status = status.await.unwrap();
+        assert_eq!(status.listeners, 0);
+        assert_eq!(status.peak, 0);
+
+        // Still fed while nobody listens
+        let now = channel.time.now() + Duration::from_secs(1);
+        channel.run(now).await;
+        assert!(channel.pause_time.is_none());
+        channel.run(now + Duration::from_secs(1)).await;
+        assert!(channel.pause_time.is_none());
+        assert!(channel.output.bitrate().is_some());
+    }
+
+    #[tokio::test]
+    async fn cancelled_prefetch() {
+        let mut channel = Channel::new(Tracks::new(&[(0, Format::MP3)]), Default::default());
+        let mut playlist = channel.playlist.take().unwrap();
+        let prefetching = tokio::spawn(async move {
+            std::future::pending::<()>().await;
+            playlist.prefetch().await;
+            playlist
+        });
+        prefetching.abort();
+        channel.prefetching = Some(prefetching);
+
+        channel.action(ChannelAction::Next).await;
+        assert!(channel.lost_playlist());
+        assert_eq!(channel.current, None);
+
+        channel.restore_playlist(Tracks::new(&[(0, Format::MP3)]));
+        assert!(!channel.lost_playlist());
+        channel.action(ChannelAction::Next).await;
+        assert_eq!(channel.current, Some(0));
     }
 }
@@ -1,19 +1,32 @@
 use std::{collections::HashMap, time::Duration};
 
-use jukebox_playlist::Playlist;
-use tokio::{sync::mpsc, time::Instant};
+use jukebox_playlist::{LibraryId, Playlist};
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    time::Instant,
+};
+use tracing::{info, warn};
 
 use crate::{
-    channel::{Channel, ChannelAction},
+    channel::{Channel, ChannelAction, ChannelStatus},
+    config::{ChannelConfig, ChannelMode},
+    segment::Segments,
     stream::Stream,
 };
 
+/// Build the playlist of a channel each time it starts.
+pub type PlaylistFactory<T> = Box<dyn Fn() -> T + Send>;
+
 pub struct ChannelManager<T: Playlist> {
     incoming: mpsc::Receiver<ChannelMessage>,
     subcriber: mpsc::Sender<ChannelMessage>,
+    control: mpsc::Receiver<ManagerAction<T>>,
+    controller: mpsc::Sender<ManagerAction<T>>,
 
+    /// Running channels
     channels: HashMap<String, Channel<T>>,
-    playlist: T,
+    /// Known channels, on demand ones start on their first request
+    factories: HashMap<String, (PlaylistFactory<T>, ChannelConfig)>,
 }
 
 struct ChannelMessage {
@@ -21,18 +34,45 @@ struct ChannelMessage {
     action: ChannelAction,
 }
 
+enum ManagerAction<T> {
+    Create {
+        name: String,
+        factory: PlaylistFactory<T>,
+        config: ChannelConfig,
+        done: oneshot::Sender<()>,
+    },
+    Remove {
+        name: String,
+        done: oneshot::Sender<()>,
+    },
+    Apply {
+        create: Vec<(String, PlaylistFactory<T>, ChannelConfig)>,
+        remove: Vec<String>,
+        done: oneshot::Sender<()>,
+    },
+}
+
+/// Create, reconfigure and remove the channels of a running manager.
+pub struct ChannelControl<T: Playlist> {
+    channel: mpsc::Sender<ManagerAction<T>>,
+}
+
 #[derive(Clone)]
 pub struct ChannelCommand {
     channel: mpsc::Sender<ChannelMessage>,
 }
 
 impl ChannelCommand {
-    pub async fn register(&self, name: impl AsRef<str>, st: &Stream) -> Result<(), std::io::Error> {
+    pub async fn register(&self, name: impl AsRef<str>) -> Result<Stream, std::io::Error> {
+        let (reply, stream) = oneshot::channel();
         self.channel
             .send(ChannelMessage {
                 name: name.as_ref().to_string(),
-                action: ChannelAction::Register(st.into()),
+                action: ChannelAction::Register(reply),
             })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        stream
             .await
             .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
     }
@@ -66,6 +106,188 @@ impl ChannelCommand {
             .await
             .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
     }
+
+    /// Songs played by a channel, the current one last.
+    pub async fn history(&self, name: impl AsRef<str>) -> Result<Vec<LibraryId>, std::io::Error> {
+        let (reply, history) = oneshot::channel();
+        self.channel
+            .send(ChannelMessage {
+                name: name.as_ref().to_string(),
+                action: ChannelAction::History(reply),
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        history
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Number of listeners currently connected to a channel.
+    pub async fn listeners(&self, name: impl AsRef<str>) -> Result<usize, std::io::Error> {
+        let (reply, listeners) = oneshot::channel();
+        self.channel
+            .send(ChannelMessage {
+                name: name.as_ref().to_string(),
+                action: ChannelAction::Listeners(reply),
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        listeners
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Current song, position and listeners of a channel.
+    pub async fn status(&self, name: impl AsRef<str>) -> Result<ChannelStatus, std::io::Error> {
+        let (reply, status) = oneshot::channel();
+        self.channel
+            .send(ChannelMessage {
+                name: name.as_ref().to_string(),
+                action: ChannelAction::Status(reply),
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        status
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Title of the song played by a channel, updated on each song.
+    pub async fn title(
+        &self,
+        name: impl AsRef<str>,
+    ) -> Result<watch::Receiver<Option<String>>, std::io::Error> {
+        let (reply, title) = oneshot::channel();
+        self.channel
+            .send(ChannelMessage {
+                name: name.as_ref().to_string(),
+                action: ChannelAction::Title(reply),
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        title
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// HLS segments of a channel, `None` if the channel has no HLS output.
+    ///
+    /// An on demand channel keeps playing while its segments are requested.
+    pub async fn hls(&self, name: impl AsRef<str>) -> Result<Option<Segments>, std::io::Error> {
+        let (reply, segments) = oneshot::channel();
+        self.channel
+            .send(ChannelMessage {
+                name: name.as_ref().to_string(),
+                action: ChannelAction::Hls(reply),
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        segments
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// CMAF segments of a channel, `None` if the channel has no DASH output.
+    ///
+    /// An on demand channel keeps playing while its segments are requested.
+    pub async fn dash(&self, name: impl AsRef<str>) -> Result<Option<Segments>, std::io::Error> {
+        let (reply, segments) = oneshot::channel();
+        self.channel
+            .send(ChannelMessage {
+                name: name.as_ref().to_string(),
+                action: ChannelAction::Dash(reply),
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        segments
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl<T> ChannelControl<T>
+where
+    T: Playlist,
+{
+    /// Create a channel, or reconfigure it when it already exists.
+    pub async fn create(
+        &self,
+        name: impl Into<String>,
+        factory: impl Fn() -> T + Send + 'static,
+        config: ChannelConfig,
+    ) -> Result<(), std::io::Error> {
+        let (done, created) = oneshot::channel();
+        self.channel
+            .send(ManagerAction::Create {
+                name: name.into(),
+                factory: Box::new(factory),
+                config,
+                done,
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        created
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Remove a channel, its listeners are disconnected.
+    pub async fn remove(&self, name: impl Into<String>) -> Result<(), std::io::Error> {
+        let (done, removed) = oneshot::channel();
+        self.channel
+            .send(ManagerAction::Remove {
+                name: name.into(),
+                done,
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        removed
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    /// Create or reconfigure the channels of `create` and remove the ones of
+    /// `remove` at once, no channel changes if the manager can't be reached.
+    pub async fn apply(
+        &self,
+        create: Vec<(String, PlaylistFactory<T>, ChannelConfig)>,
+        remove: Vec<String>,
+    ) -> Result<(), std::io::Error> {
+        let (done, applied) = oneshot::channel();
+        self.channel
+            .send(ManagerAction::Apply {
+                create,
+                remove,
+                done,
+            })
+            .await
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+        applied
+            .await
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+impl<T> Clone for ChannelControl<T>
+where
+    T: Playlist,
+{
+    fn clone(&self) -> Self {
+        Self {
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<T> From<&ChannelManager<T>> for ChannelControl<T>
+where
+    T: Playlist,
+{
+    fn from(value: &ChannelManager<T>) -> Self {
+        Self {
+            channel: value.controller.clone(),
+        }
+    }
 }
 
 impl<T> From<&ChannelManager<T>> for ChannelCommand
@@ -79,20 +301,80 @@ where
     }
 }
 
-impl<T> ChannelManager<T>
+impl<T> Default for ChannelManager<T>
 where
     T: Playlist,
 {
-    const CHANNEL_REFRESH: u32 = 100_000_000; // 100 ms
-    pub fn new(playlist: T) -> Self {
+    fn default() -> Self {
         let (subcriber, incoming) = mpsc::channel(128);
+        let (controller, control) = mpsc::channel(16);
         Self {
             incoming,
             subcriber,
+            control,
+            controller,
             channels: Default::default(),
-            playlist,
+            factories: Default::default(),
         }
     }
+}
+
+impl<T> ChannelManager<T>
+where
+    T: Playlist + 'static,
+{
+    const CHANNEL_REFRESH: u32 = 100_000_000; // 100 ms
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Declare a channel, or reconfigure it when it already exists.
+    ///
+    /// A continuous channel or one pushed to Icecast starts immediately, an
+    /// on demand one on its first request.
+    pub fn create(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn() -> T + Send + 'static,
+        config: ChannelConfig,
+    ) {
+        self.insert(name.into(), Box::new(factory), config)
+    }
+
+    fn insert(&mut self, name: String, factory: PlaylistFactory<T>, config: ChannelConfig) {
+        match self.channels.get_mut(&name) {
+            Some(channel) => {
+                info!("channel: reconfigure {}", name);
+                channel.reconfigure(factory(), config.clone());
+            }
+            None if config.mode == ChannelMode::Continuous || !config.icecast.is_empty() => {
+                info!("channel: start {}", name);
+                self.channels
+                    .insert(name.clone(), Channel::new(factory(), config.clone()));
+            }
+            None if self.factories.contains_key(&name) => info!("channel: reconfigure {}", name),
+            None => info!("channel: create {}", name),
+        }
+        self.factories.insert(name, (factory, config));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        if self.factories.remove(name).is_some() {
+            info!("channel: remove {}", name);
+        }
+        self.channels.remove(name);
+    }
+
+    /// Running channel, started from its factory if needed.
+    fn channel(&mut self, name: &str) -> Option<&mut Channel<T>> {
+        if !self.channels.contains_key(name) {
+            let (factory, config) = self.factories.get(name)?;
+            info!("channel: start {}", name);
+            self.channels
+                .insert(name.to_string(), Channel::new(factory(), config.clone()));
+        }
+        self.channels.get_mut(name)
+    }
 
     pub async fn run(&mut self) {
         let duration = Duration::new(0, Self::CHANNEL_REFRESH);
@@ -101,18 +383,55 @@ where
         loop {
             tokio::select! {
                 _ = tokio::time::sleep_until(next) => {
-                    for (_, channel) in self.channels.iter_mut() {
+                    for (name, channel) in self.channels.iter_mut() {
                         channel.run(next).await;
+                        restore(name, channel, &self.factories);
                     }
                     next += duration;
                 }
-                Some(msg) = self.incoming.recv() => {
-                    self.channels
-                        .entry(msg.name)
-                        .or_insert_with(|| Channel::new(self.playlist.clone()))
-                        .action(msg.action).await;
-                }
+                Some(action) = self.control.recv() => match action {
+                    ManagerAction::Create { name, factory, config, done } => {
+                        self.insert(name, factory, config);
+                        let _ = done.send(());
+                    }
+                    ManagerAction::Remove { name, done } => {
+                        self.remove(&name);
+                        let _ = done.send(());
+                    }
+                    ManagerAction::Apply { create, remove, done } => {
+                        for (name, factory, config) in create {
+                            self.insert(name, factory, config);
+                        }
+                        for name in remove {
+                            self.remove(&name);
+                        }
+                        let _ = done.send(());
+                    }
+                },
+                Some(msg) = self.incoming.recv() => match self.channel(&msg.name) {
+                    Some(channel) => {
+                        channel.action(msg.action).await;
+                        if let Some(channel) = self.channels.get_mut(&msg.name) {
+                            restore(&msg.name, channel, &self.factories);
+                        }
+                    }
+                    None => warn!("channel: unknown channel {}", msg.name),
+                },
             }
         }
     }
 }
+
+/// Rebuild the playlist a channel lost with its cancelled prefetch task.
+fn restore<T: Playlist + 'static>(
+    name: &str,
+    channel: &mut Channel<T>,
+    factories: &HashMap<String, (PlaylistFactory<T>, ChannelConfig)>,
+) {
+    if channel.lost_playlist()
+        && let Some((factory, _)) = factories.get(name)
+    {
+        info!("channel: rebuild the playlist of {}", name);
+        channel.restore_playlist(factory());
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+    time::Duration,
+};
+
+use jukebox_decoder::Format;
+use jukebox_playlist::{BoxFuture, LibraryId};
+
+/// How a channel behaves while nobody is listening.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelMode {
+    /// Freeze the channel when the last listener leaves and resume where it stopped.
+    #[default]
+    OnDemand,
+    /// Keep the schedule on wall-clock time, like a live broadcast.
+    Continuous,
+}
+
+/// Per channel settings used when a channel is created.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelConfig {
+    pub mode: ChannelMode,
+    /// Format of the streamed audio, tracks in another format are skipped.
+    /// Any format is streamed when unset.
+    pub format: Option<Format>,
+    /// Amount of already played audio sent to a listener when it connects,
+    /// at most the `stream.capacity` frames kept for the listeners.
+    pub burst: Duration,
+    pub stream: StreamConfig,
+    /// Also produce an HTTP Live Streaming output when set.
+    pub hls: Option<SegmentConfig>,
+    /// Also produce a DASH output of CMAF segments when set.
+    pub dash: Option<SegmentConfig>,
+    /// Icecast servers the channel is pushed to, it plays while any is set
+    /// even without listeners, which don't count the servers.
+    pub icecast: Vec<IcecastConfig>,
+    /// Title of the songs, sent as stream metadata.
+    pub titles: Option<Titles>,
+    /// Record the songs the channel starts to stream, not the skipped ones.
+    pub plays: Option<Plays>,
+}
+
+/// Resolve the title of a song, like `Artist - Title`.
+#[derive(Clone)]
+pub struct Titles(pub Arc<dyn Fn(LibraryId) -> BoxFuture<'static, Option<String>> + Send + Sync>);
+
+impl Debug for Titles {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Titles")
+    }
+}
+
+/// Record that a song started playing, like `Library::played`.
+#[derive(Clone)]
+pub struct Plays(pub Arc<dyn Fn(LibraryId) -> BoxFuture<'static, ()> + Send + Sync>);
+
+impl Debug for Plays {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Plays")
+    }
+}
+
+/// What to do with a listener more than `capacity` frames behind the channel.
+///
+/// Frames are shared by the listeners, the ones a slow listener loses are
+/// always the oldest it didn't read yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlowClientPolicy {
+    /// Skip the lost frames and continue with the oldest frame still kept.
+    #[default]
+    DropOldest,
+    /// Skip the lost frames like `DropOldest`, and close the listener once
+    /// more than `dropped` frames were lost.
+    Disconnect { dropped: usize },
+    /// Skip the lost frames and also the oldest half of the frames still
+    /// kept, to restart `capacity / 2` frames behind the channel. The
+    /// listener loses more frames at once but has room to fall behind again
+    /// before its next gap. Nothing slows the channel down.
+    SkipHalf,
+}
+
+/// Bounds applied to every listener of a channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamConfig {
+    /// Number of frames kept for the listeners, a listener further behind
+    /// loses frames. It also bounds the burst sent on connection.
+    pub capacity: usize,
+    pub policy: SlowClientPolicy,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            // About 13 seconds of 44.1 kHz mp3
+            capacity: 512,
+            policy: SlowClientPolicy::default(),
+        }
+    }
+}
+
+/// Segments of a segmented output of a channel, like HLS or DASH.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SegmentConfig {
+    /// Target duration of a segment, a segment is also cut when the song changes.
+    pub segment: Duration,
+    /// Number of segments listed in the media playlist.
+    pub window: usize,
+}
+
+impl Default for SegmentConfig {
+    fn default() -> Self {
+        Self {
+            segment: Duration::from_secs(6),
+            window: 6,
+        }
+    }
+}
+
+/// Request used to start streaming to an Icecast server.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SourceProtocol {
+    /// HTTP `PUT`, Icecast 2.4 and later.
+    #[default]
+    Put,
+    /// Legacy `SOURCE` request, older Icecast and compatible servers.
+    Source,
+}
+
+/// An Icecast mount a channel is pushed to as a source client.
+///
+/// The source isn't counted in the listeners of the channel, but the channel
+/// keeps playing for it, also in on-demand mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IcecastConfig {
+    /// `host:port` of the server
+    pub address: String,
+    pub mount: String,
+    pub user: String,
+    pub password: String,
+    pub protocol: SourceProtocol,
+    /// Stream description sent in the `ice-*` headers
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub genre: Option<String>,
+    /// List the stream in the public directories of the server
+    pub public: bool,
+}
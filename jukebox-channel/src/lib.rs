@@ -1,6 +1,15 @@
 mod channel;
 mod channel_manager;
+mod config;
+mod icecast;
+mod segment;
 mod stream;
 
-pub use channel_manager::{ChannelCommand, ChannelManager};
-pub use stream::{Stream, StreamWeak};
+pub use channel::ChannelStatus;
+pub use channel_manager::{ChannelCommand, ChannelControl, ChannelManager, PlaylistFactory};
+pub use config::{
+    ChannelConfig, ChannelMode, IcecastConfig, Plays, SegmentConfig, SlowClientPolicy,
+    SourceProtocol, StreamConfig, Titles,
+};
+pub use segment::{Progress, Segment, Segments, Window};
+pub use stream::Stream;
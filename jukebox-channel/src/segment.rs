@@ -0,0 +1,277 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jukebox_decoder::Frame;
+use jukebox_playlist::LibraryId;
+use tokio::{sync::watch, time::Instant};
+
+use crate::config::SegmentConfig;
+
+/// Audio of a channel between two cuts, made of whole frames.
+#[derive(Debug)]
+pub struct Segment {
+    pub sequence: u64,
+    pub duration: Duration,
+    /// Position of the segment since the output started
+    pub start: Duration,
+    /// Number of the first frame since the output started
+    pub frame: u64,
+    /// Song playing at the start of the segment
+    pub track: Option<LibraryId>,
+    /// The segment doesn't follow the previous one, the channel was paused
+    pub discontinuity: bool,
+    pub frames: Vec<Frame>,
+}
+
+/// Last segments of a channel, the oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct Window {
+    pub segments: Vec<Arc<Segment>>,
+    /// Number of discontinuities which left the window
+    pub discontinuity_sequence: u64,
+}
+
+/// Frames of a segment, which may still be produced.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    /// Position of the segment since the output started
+    pub start: Duration,
+    /// Number of the first of `frames` since the output started
+    pub frame: u64,
+    pub frames: Vec<Frame>,
+    /// No frame will be added to the segment
+    pub complete: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    window: Window,
+    /// Frames of the segment being produced
+    pending: Vec<Frame>,
+    /// Sequence number, position and number of the first frame of the
+    /// segment being produced
+    next: (u64, Duration, u64),
+    /// Wall-clock time of the start of the output, moved forward by pauses
+    origin: Option<SystemTime>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    config: SegmentConfig,
+    state: Mutex<State>,
+    /// Signaled each time frames are published
+    changes: watch::Sender<()>,
+    /// Last request of a client, the output counts as a listener for a while
+    accessed: Mutex<Option<Instant>>,
+}
+
+/// Read side of a segmented output of a channel.
+#[derive(Debug, Clone)]
+pub struct Segments {
+    shared: Arc<Shared>,
+}
+
+/// Cut the frames played by a channel into segments.
+pub(crate) struct Segmenter {
+    segments: Segments,
+    /// Frames not published yet
+    frames: Vec<Frame>,
+    duration: Duration,
+    start: Duration,
+    /// Number of the first frame of the current segment
+    frame: u64,
+    track: Option<LibraryId>,
+    sequence: u64,
+    discontinuity: bool,
+}
+
+impl Segments {
+    /// Clients poll the playlist about once per segment, a few missed polls
+    /// mean they left.
+    const IDLE_SEGMENTS: u32 = 3;
+
+    pub fn config(&self) -> SegmentConfig {
+        self.shared.config
+    }
+
+    pub fn window(&self) -> Window {
+        self.touch();
+        self.shared.state.lock().unwrap().window.clone()
+    }
+
+    pub fn get(&self, sequence: u64) -> Option<Arc<Segment>> {
+        self.touch();
+        let state = self.shared.state.lock().unwrap();
+        let first = state.window.segments.first()?.sequence;
+        state
+            .window
+            .segments
+            .get(sequence.checked_sub(first)? as usize)
+            .cloned()
+    }
+
+    /// Frames of a segment from the `from` one, also while it is produced.
+    ///
+    /// Returns `None` if the segment left the window or isn't started yet.
+    pub fn progress(&self, sequence: u64, from: usize) -> Option<Progress> {
+        self.touch();
+        let state = self.shared.state.lock().unwrap();
+        if sequence == state.next.0 {
+            return Some(Progress {
+                start: state.next.1,
+                frame: state.next.2 + from as u64,
+                frames: state.pending.get(from..).unwrap_or_default().to_vec(),
+                complete: false,
+            });
+        }
+        let first = state.window.segments.first()?.sequence;
+        let segment = state
+            .window
+            .segments
+            .get(sequence.checked_sub(first)? as usize)?;
+        Some(Progress {
+            start: segment.start,
+            frame: segment.frame + from as u64,
+            frames: segment.frames.get(from..).unwrap_or_default().to_vec(),
+            complete: true,
+        })
+    }
+
+    /// Sequence number of the segment being produced.
+    pub fn next(&self) -> u64 {
+        self.shared.state.lock().unwrap().next.0
+    }
+
+    /// Wall-clock time matching the start of the output.
+    pub fn origin(&self) -> SystemTime {
+        let state = self.shared.state.lock().unwrap();
+        state.origin.unwrap_or_else(SystemTime::now)
+    }
+
+    /// Notified each time frames are added to the output.
+    pub fn changes(&self) -> watch::Receiver<()> {
+        self.shared.changes.subscribe()
+    }
+
+    fn touch(&self) {
+        *self.shared.accessed.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// Whether a client requested the output recently.
+    pub(crate) fn active(&self, now: Instant) -> bool {
+        let idle = self.shared.config.segment * Self::IDLE_SEGMENTS;
+        self.shared
+            .accessed
+            .lock()
+            .unwrap()
+            .is_some_and(|accessed| now.saturating_duration_since(accessed) < idle)
+    }
+}
+
+impl Segmenter {
+    pub(crate) fn new(config: SegmentConfig) -> Self {
+        // Segments last more than a second, a restarted output never
+        // reuses the sequence numbers of a previous one
+        let sequence = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default();
+        Self {
+            segments: Segments {
+                shared: Arc::new(Shared {
+                    config,
+                    state: Mutex::new(State {
+                        next: (sequence, Duration::ZERO, 0),
+                        ..Default::default()
+                    }),
+                    changes: watch::channel(()).0,
+                    accessed: Default::default(),
+                }),
+            },
+            frames: Vec::new(),
+            duration: Duration::ZERO,
+            start: Duration::ZERO,
+            frame: 0,
+            track: None,
+            sequence,
+            discontinuity: false,
+        }
+    }
+
+    pub(crate) fn config(&self) -> SegmentConfig {
+        self.segments.config()
+    }
+
+    pub(crate) fn segments(&self) -> &Segments {
+        &self.segments
+    }
+
+    /// Add a frame of `track`, a new segment starts with each song.
+    pub(crate) fn push(&mut self, frame: &Frame, track: Option<LibraryId>) {
+        if track != self.track && self.duration > Duration::ZERO {
+            self.cut();
+        }
+        if self.duration == Duration::ZERO {
+            self.track = track;
+        }
+        self.frames.push(frame.clone());
+        self.duration += frame.duration();
+        if self.duration >= self.config().segment {
+            self.cut();
+        }
+    }
+
+    /// Make the frames pushed since the last call available to clients.
+    pub(crate) fn flush(&mut self) {
+        let mut state = self.segments.shared.state.lock().unwrap();
+        state.origin.get_or_insert_with(SystemTime::now);
+        state.pending.append(&mut self.frames);
+        drop(state);
+        self.segments.shared.changes.send_replace(());
+    }
+
+    /// Mark the next segment as not following the previous one.
+    pub(crate) fn discontinuity(&mut self) {
+        if self.duration > Duration::ZERO {
+            self.cut();
+        }
+        self.discontinuity = self.start > Duration::ZERO;
+        let mut state = self.segments.shared.state.lock().unwrap();
+        state.origin = SystemTime::now().checked_sub(self.start);
+    }
+
+    fn cut(&mut self) {
+        let mut state = self.segments.shared.state.lock().unwrap();
+        let mut frames = std::mem::take(&mut state.pending);
+        frames.append(&mut self.frames);
+        let frame = self.frame;
+        self.frame += frames.len() as u64;
+        state.window.segments.push(Arc::new(Segment {
+            sequence: self.sequence,
+            duration: self.duration,
+            start: self.start,
+            frame,
+            track: self.track,
+            discontinuity: std::mem::take(&mut self.discontinuity),
+            frames,
+        }));
+        let excess = state
+            .window
+            .segments
+            .len()
+            .saturating_sub(self.segments.shared.config.window.max(1));
+        let removed = state
+            .window
+            .segments
+            .drain(..excess)
+            .filter(|segment| segment.discontinuity)
+            .count();
+        state.window.discontinuity_sequence += removed as u64;
+
+        self.sequence += 1;
+        self.start += std::mem::take(&mut self.duration);
+        state.next = (self.sequence, self.start, self.frame);
+    }
+}
@@ -1,81 +1,289 @@
 use std::{
-    borrow::BorrowMut,
-    collections::VecDeque,
     convert::Infallible,
-    ops::Deref,
-    sync::{Arc, Weak},
-    task::{Poll, Waker},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering, fence},
+    },
+    task::Poll,
+    time::Duration,
 };
 
+use arc_swap::{ArcSwap, ArcSwapOption};
 use bytes::Bytes;
-use pin_project::pin_project;
-use tokio::sync::RwLock;
+use futures::task::AtomicWaker;
+use jukebox_decoder::Frame;
+use tracing::info;
 
-#[derive(Default)]
-struct StreamRaw {
-    waker: Option<Waker>,
-    frames: VecDeque<Bytes>,
-}
+use crate::config::{SlowClientPolicy, StreamConfig};
 
-#[derive(Default)]
-struct StreamLock {
-    inner: RwLock<StreamRaw>,
+struct Slot {
+    seq: u64,
+    duration: Duration,
+    data: Bytes,
 }
 
-impl Deref for StreamLock {
-    type Target = RwLock<StreamRaw>;
+/// Frames shared by every listener of a channel.
+///
+/// The channel writes each frame once, listeners only keep a cursor on the
+/// sequence number of the next frame to read.
+///
+/// The ring is the queue of every listener: a listener more than `capacity`
+/// frames behind lost the oldest ones, the slow client policy only decides
+/// where its cursor restarts and when it is disconnected.
+struct Ring {
+    slots: Box<[ArcSwapOption<Slot>]>,
+    /// Sequence number of the next written frame
+    head: AtomicU64,
+    listeners: AtomicUsize,
+    /// Frames lost by every slow listener
+    dropped: AtomicU64,
+    closed: AtomicBool,
+    /// Waker of each listener, registered once when it subscribes
+    waiters: ArcSwap<Vec<Arc<Waiter>>>,
+    /// Number of listeners waiting for a frame
+    waiting: AtomicUsize,
+    config: StreamConfig,
+}
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
-    }
+/// Wake-up of a listener waiting for the next frame.
+#[derive(Default)]
+struct Waiter {
+    waker: AtomicWaker,
+    parked: AtomicBool,
 }
 
-#[derive(Clone)]
-pub struct StreamWeak {
-    inner: Weak<StreamLock>,
+/// Writer side of the ring, owned by the channel.
+pub(crate) struct Broadcast {
+    ring: Arc<Ring>,
 }
 
-#[derive(Default)]
-#[pin_project]
 pub struct Stream {
-    inner: Arc<StreamLock>,
+    ring: Arc<Ring>,
+    waiter: Arc<Waiter>,
+    cursor: u64,
+    dropped: u64,
+    /// Counted in the listeners of the ring
+    counted: bool,
 }
 
-impl From<&Stream> for StreamWeak {
-    fn from(value: &Stream) -> Self {
+impl Ring {
+    fn get(&self, seq: u64) -> Option<Arc<Slot>> {
+        let slot = self.slots[(seq % self.slots.len() as u64) as usize].load_full()?;
+        (slot.seq == seq).then_some(slot)
+    }
+
+    /// Wake the listeners waiting for a frame, nothing is done if none waits.
+    fn wake(&self) {
+        // Pairs with the fence of a parking listener: either it sees the new
+        // head, or it is counted here
+        fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::Relaxed) == 0 {
+            return;
+        }
+        for waiter in self.waiters.load().iter() {
+            if waiter.parked.swap(false, Ordering::AcqRel) {
+                self.waiting.fetch_sub(1, Ordering::Relaxed);
+                waiter.waker.wake();
+            }
+        }
+    }
+
+    /// Create a listener starting `burst` of audio before the last written
+    /// frame, `counted` in the listeners of the ring.
+    fn subscribe(ring: &Arc<Ring>, burst: Duration, counted: bool) -> Stream {
+        let head = ring.head.load(Ordering::Acquire);
+        let mut cursor = head;
+        let mut duration = Duration::ZERO;
+        while cursor > 0 && duration < burst {
+            match ring.get(cursor - 1) {
+                Some(slot) => duration += slot.duration,
+                None => break,
+            }
+            cursor -= 1;
+        }
+
+        if counted {
+            ring.listeners.fetch_add(1, Ordering::Relaxed);
+        }
+        let waiter = Arc::new(Waiter::default());
+        ring.waiters.rcu(|waiters| {
+            let mut waiters = Vec::clone(waiters);
+            waiters.push(waiter.clone());
+            waiters
+        });
+        Stream {
+            ring: ring.clone(),
+            waiter,
+            cursor,
+            dropped: 0,
+            counted,
+        }
+    }
+}
+
+impl Broadcast {
+    pub(crate) fn new(config: StreamConfig) -> Self {
+        let slots = (0..config.capacity.max(1))
+            .map(|_| ArcSwapOption::empty())
+            .collect();
         Self {
-            inner: Arc::downgrade(&value.inner),
+            ring: Arc::new(Ring {
+                slots,
+                head: AtomicU64::new(0),
+                listeners: AtomicUsize::new(0),
+                dropped: AtomicU64::new(0),
+                closed: AtomicBool::new(false),
+                waiters: Default::default(),
+                waiting: AtomicUsize::new(0),
+                config,
+            }),
         }
     }
+
+    pub(crate) fn push(&self, frame: &Frame) {
+        let ring = &self.ring;
+        let seq = ring.head.load(Ordering::Relaxed);
+        let duration = frame.duration();
+        ring.slots[(seq % ring.slots.len() as u64) as usize].store(Some(Arc::new(Slot {
+            seq,
+            duration,
+            data: frame.data.clone(),
+        })));
+        ring.head.store(seq + 1, Ordering::Release);
+        ring.wake();
+    }
+
+    /// Create a listener starting `burst` of audio before the last written frame.
+    pub(crate) fn subscribe(&self, burst: Duration) -> Stream {
+        Ring::subscribe(&self.ring, burst, true)
+    }
+
+    /// Create a reader at the last written frame which isn't counted in the
+    /// listeners, for the outputs fed by the channel itself.
+    pub(crate) fn subscribe_uncounted(&self) -> Stream {
+        Ring::subscribe(&self.ring, Duration::ZERO, false)
+    }
+
+    pub(crate) fn listeners(&self) -> usize {
+        self.ring.listeners.load(Ordering::Relaxed)
+    }
+
+    /// Frames lost by slow listeners since the ring was created.
+    pub(crate) fn dropped(&self) -> u64 {
+        self.ring.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Average bitrate of the frames in the ring, in kbit/s.
+    pub(crate) fn bitrate(&self) -> Option<u32> {
+        let (bytes, duration) = self
+            .ring
+            .slots
+            .iter()
+            .filter_map(|slot| slot.load_full())
+            .fold((0, Duration::ZERO), |(bytes, duration), slot| {
+                (bytes + slot.data.len(), duration + slot.duration)
+            });
+        (!duration.is_zero())
+            .then(|| (bytes as f64 * 8.0 / 1000.0 / duration.as_secs_f64()).round() as u32)
+    }
 }
 
-impl StreamRaw {
-    fn push(&mut self, data: &Bytes) {
-        self.frames.push_back(data.clone());
-        self.waker.as_ref().map(Waker::wake_by_ref);
+impl Drop for Broadcast {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+        self.ring.wake();
+    }
+}
+
+impl Stream {
+    /// Number of frames this listener lost because it was too slow.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// A new listener at the last written frame, `None` once the channel
+    /// stopped. Lets an ended listener tell a disconnection for being too
+    /// slow from the end of the channel.
+    pub(crate) fn resubscribe(&self) -> Option<Stream> {
+        match self.ring.closed.load(Ordering::Acquire) {
+            true => None,
+            false => Some(Ring::subscribe(&self.ring, Duration::ZERO, self.counted)),
+        }
     }
 
-    fn get(&mut self, cx: &mut std::task::Context) -> Option<Bytes> {
-        match self.frames.borrow_mut().pop_front() {
-            None => {
-                // No data available
-                self.waker = Some(cx.waker().clone());
-                None
+    /// Move the cursor forward when the listener fell behind the ring,
+    /// returns `false` if the listener must be disconnected.
+    ///
+    /// The frames lost are always the oldest ones, they are overwritten by the
+    /// channel: `DropOldest` and `Disconnect` restart at the oldest frame still
+    /// in the ring, `SkipHalf` skips ahead to half of the ring at once so the
+    /// listener loses frames in a single gap rather than every few frames.
+    fn catch_up(&mut self, head: u64) -> bool {
+        let config = &self.ring.config;
+        let capacity = self.ring.slots.len() as u64;
+        if head - self.cursor <= capacity && self.ring.get(self.cursor).is_some() {
+            return true;
+        }
+
+        let cursor = match config.policy {
+            SlowClientPolicy::SkipHalf => head.saturating_sub(capacity / 2),
+            // The writer may be overwriting the oldest slot, keep a margin of one frame
+            _ => head + 1 - capacity.min(head),
+        };
+        let dropped = cursor.saturating_sub(self.cursor);
+        self.dropped += dropped;
+        self.ring.dropped.fetch_add(dropped, Ordering::Relaxed);
+        self.cursor = cursor;
+
+        match config.policy {
+            SlowClientPolicy::Disconnect { dropped } if self.dropped > dropped as u64 => {
+                info!(
+                    "stream: disconnect slow client after {} dropped frames",
+                    self.dropped
+                );
+                false
             }
-            v => v,
+            _ => true,
         }
     }
-}
 
-impl StreamWeak {
-    pub(crate) async fn push(&self, data: &Bytes) {
-        if let Some(stream) = self.inner.upgrade() {
-            stream.inner.write().await.push(data);
+    fn next_frame(&mut self) -> Option<Poll<Option<Bytes>>> {
+        let head = self.ring.head.load(Ordering::Acquire);
+        if self.cursor >= head {
+            return None;
+        }
+        if !self.catch_up(head) {
+            return Some(Poll::Ready(None));
         }
+
+        let slot = self.ring.get(self.cursor)?;
+        self.cursor += 1;
+        Some(Poll::Ready(Some(slot.data.clone())))
     }
+}
 
-    pub(crate) fn active(&self) -> bool {
-        self.inner.strong_count() != 0
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let ring = &self.ring;
+        if self.counted {
+            ring.listeners.fetch_sub(1, Ordering::Relaxed);
+        }
+        if self.dropped > 0 {
+            info!(
+                "stream: listener left after {} dropped frames",
+                self.dropped
+            );
+        }
+        if self.waiter.parked.swap(false, Ordering::AcqRel) {
+            ring.waiting.fetch_sub(1, Ordering::Relaxed);
+        }
+        ring.waiters.rcu(|waiters| {
+            waiters
+                .iter()
+                .filter(|waiter| !Arc::ptr_eq(waiter, &self.waiter))
+                .cloned()
+                .collect::<Vec<_>>()
+        });
     }
 }
 
@@ -86,14 +294,161 @@ impl ::futures::Stream for Stream {
         self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> Poll<Option<Self::Item>> {
-        let stream = self.project().inner;
-        let mut res = Box::pin(stream.inner.write());
-        match res.as_mut().poll(cx) {
-            Poll::Ready(mut guard) => guard
-                .get(cx)
-                .map(|d| Poll::Ready(Some(Ok(d))))
-                .unwrap_or(Poll::Pending),
-            Poll::Pending => Poll::Pending,
+        let stream = self.get_mut();
+        loop {
+            if let Some(poll) = stream.next_frame() {
+                return poll.map(|d| d.map(Ok));
+            }
+            if stream.ring.closed.load(Ordering::Acquire) {
+                return Poll::Ready(None);
+            }
+
+            // No data available, check again once parked to not miss a push
+            stream.waiter.waker.register(cx.waker());
+            if !stream.waiter.parked.swap(true, Ordering::AcqRel) {
+                stream.ring.waiting.fetch_add(1, Ordering::Relaxed);
+            }
+            fence(Ordering::SeqCst);
+            if stream.cursor >= stream.ring.head.load(Ordering::Acquire)
+                && !stream.ring.closed.load(Ordering::Acquire)
+            {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        pin::Pin,
+        sync::atomic::AtomicUsize,
+        task::{Context, Poll},
+    };
+
+    use futures::{
+        FutureExt, Stream as _, StreamExt,
+        task::{ArcWake, waker},
+    };
+
+    use super::*;
+
+    fn broadcast(capacity: usize, policy: SlowClientPolicy) -> Broadcast {
+        Broadcast::new(StreamConfig { capacity, policy })
+    }
+
+    fn push(broadcast: &Broadcast, frames: std::ops::Range<u8>) {
+        for i in frames {
+            broadcast.push(&Frame::new(Bytes::from(vec![i]), 1152, 44100));
+        }
+    }
+
+    /// Frames available without waiting, `None` once the stream ended.
+    fn read(stream: &mut Stream) -> Option<Vec<u8>> {
+        let mut frames = Vec::new();
+        loop {
+            match stream.next().now_or_never() {
+                Some(Some(Ok(data))) => frames.push(data[0]),
+                Some(None) => return None,
+                None => return Some(frames),
+            }
         }
     }
+
+    #[test]
+    fn drop_oldest_restarts_at_oldest_frame() {
+        let broadcast = broadcast(4, SlowClientPolicy::DropOldest);
+        let mut stream = broadcast.subscribe(Duration::ZERO);
+        push(&broadcast, 0..10);
+        assert_eq!(read(&mut stream), Some(vec![7, 8, 9]));
+        assert_eq!(stream.dropped(), 7);
+        assert_eq!(broadcast.dropped(), 7);
+    }
+
+    #[test]
+    fn skip_half_restarts_at_half_capacity() {
+        let broadcast = broadcast(4, SlowClientPolicy::SkipHalf);
+        let mut stream = broadcast.subscribe(Duration::ZERO);
+        push(&broadcast, 0..10);
+        assert_eq!(read(&mut stream), Some(vec![8, 9]));
+        assert_eq!(stream.dropped(), 8);
+    }
+
+    #[test]
+    fn disconnect_after_threshold() {
+        let broadcast = broadcast(4, SlowClientPolicy::Disconnect { dropped: 3 });
+        let mut stream = broadcast.subscribe(Duration::ZERO);
+        push(&broadcast, 0..6);
+        assert_eq!(read(&mut stream), Some(vec![3, 4, 5]));
+        push(&broadcast, 6..12);
+        assert_eq!(read(&mut stream), None);
+    }
+
+    #[test]
+    fn resubscribe_until_closed() {
+        let broadcast = broadcast(4, SlowClientPolicy::Disconnect { dropped: 0 });
+        let mut stream = broadcast.subscribe(Duration::ZERO);
+        push(&broadcast, 0..8);
+        assert_eq!(read(&mut stream), None);
+
+        let mut resumed = stream.resubscribe().unwrap();
+        push(&broadcast, 8..10);
+        assert_eq!(read(&mut resumed), Some(vec![8, 9]));
+
+        drop(broadcast);
+        assert!(resumed.resubscribe().is_none());
+    }
+
+    #[test]
+    fn burst_is_bounded_by_capacity() {
+        let broadcast = broadcast(4, SlowClientPolicy::DropOldest);
+        push(&broadcast, 0..10);
+        // Two frames of 26 ms
+        let mut stream = broadcast.subscribe(Duration::from_millis(40));
+        assert_eq!(read(&mut stream), Some(vec![8, 9]));
+        let mut stream = broadcast.subscribe(Duration::from_secs(10));
+        assert_eq!(read(&mut stream), Some(vec![6, 7, 8, 9]));
+        assert_eq!(stream.dropped(), 0);
+    }
+
+    #[test]
+    fn closed_when_broadcast_dropped() {
+        let broadcast = broadcast(4, SlowClientPolicy::DropOldest);
+        let mut stream = broadcast.subscribe(Duration::ZERO);
+        push(&broadcast, 0..2);
+        drop(broadcast);
+        assert_eq!(read(&mut stream), None);
+    }
+
+    #[derive(Default)]
+    struct Wakes(AtomicUsize);
+
+    impl ArcWake for Wakes {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn waiting_listener_woken_once() {
+        let broadcast = broadcast(4, SlowClientPolicy::DropOldest);
+        let mut stream = broadcast.subscribe(Duration::ZERO);
+        let wakes = Arc::new(Wakes::default());
+        let waker = waker(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        assert!(Pin::new(&mut stream).poll_next(&mut cx).is_pending());
+        assert_eq!(broadcast.ring.waiting.load(Ordering::Relaxed), 1);
+        push(&broadcast, 0..3);
+        assert_eq!(wakes.0.load(Ordering::Relaxed), 1);
+        assert_eq!(broadcast.ring.waiting.load(Ordering::Relaxed), 0);
+
+        assert!(matches!(
+            Pin::new(&mut stream).poll_next(&mut cx),
+            Poll::Ready(Some(Ok(_)))
+        ));
+        drop(stream);
+        assert!(broadcast.ring.waiters.load().is_empty());
+    }
 }
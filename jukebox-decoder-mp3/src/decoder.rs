@@ -1,6 +1,8 @@
+use std::io::{Read, Seek};
+
 use bytes::Bytes;
 
-use jukebox_decoder::{Decoder, Stream};
+use jukebox_decoder::{Decoder, Format, Metadata, Stream};
 
 use super::stream::Mp3Stream;
 
@@ -12,7 +14,15 @@ impl Decoder for Mp3Decoder {
         "mp3"
     }
 
+    fn format() -> Format {
+        Format::MP3
+    }
+
     fn decode(buf: Bytes) -> Box<dyn Stream> {
         Box::new(Mp3Stream::new(buf))
     }
+
+    fn metadata<R: Read + Seek>(reader: &mut R) -> Metadata {
+        super::frame::metadata(reader)
+    }
 }
@@ -1,4 +1,7 @@
+use std::io::{Read, Seek, SeekFrom};
+
 use bytes::{Buf, Bytes};
+use jukebox_decoder::Metadata;
 
 pub(crate) struct Id3V1 {
     // data: Bytes,
@@ -6,6 +9,89 @@ pub(crate) struct Id3V1 {
 
 const ID3V1_SIZE: usize = 128;
 
+pub(super) const GENRES: [&str; 80] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+];
+
 impl TryFrom<&mut Bytes> for Id3V1 {
     type Error = jukebox_decoder::Error;
     fn try_from(value: &mut Bytes) -> Result<Self, Self::Error> {
@@ -22,3 +108,79 @@ impl TryFrom<&mut Bytes> for Id3V1 {
         }
     }
 }
+
+impl Id3V1 {
+    /// Tags of the ID3v1 tag at the end of a file.
+    pub(super) fn read<R: Read + Seek>(reader: &mut R) -> Option<Metadata> {
+        let mut tag = [0u8; ID3V1_SIZE];
+        reader.seek(SeekFrom::End(-(ID3V1_SIZE as i64))).ok()?;
+        reader.read_exact(&mut tag).ok()?;
+        if !tag.starts_with(b"TAG") {
+            return None;
+        }
+
+        let field = |range: std::ops::Range<usize>| latin1(&tag[range]);
+        let comment = &tag[97..127];
+        Some(Metadata {
+            title: field(3..33),
+            artist: field(33..63),
+            album: field(63..93),
+            year: field(93..97).and_then(|year| number(&year)),
+            // ID3v1.1 stores the track number at the end of the comment
+            track: (comment[28] == 0 && comment[29] != 0).then_some(comment[29] as u32),
+            genre: GENRES.get(tag[127] as usize).map(|g| g.to_string()),
+            disc: None,
+            rating: None,
+        })
+    }
+}
+
+pub(super) fn latin1(data: &[u8]) -> Option<String> {
+    let value: String = data
+        .split(|&c| c == 0)
+        .next()?
+        .iter()
+        .map(|&c| c as char)
+        .collect();
+    let value = value.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Parse the leading number of values like `3/12` or `1965-04-01`.
+pub(super) fn number<N: std::str::FromStr>(value: &str) -> Option<N> {
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn read_v1_1() {
+        let mut tag = [0u8; ID3V1_SIZE];
+        tag[..3].copy_from_slice(b"TAG");
+        tag[3..8].copy_from_slice(b"Title");
+        tag[33..39].copy_from_slice(b"Artist");
+        tag[93..97].copy_from_slice(b"1999");
+        tag[126] = 7;
+        tag[127] = 17;
+        let mut data = vec![0xFF; 64];
+        data.extend_from_slice(&tag);
+
+        let metadata = Id3V1::read(&mut Cursor::new(data)).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.album, None);
+        assert_eq!(metadata.year, Some(1999));
+        assert_eq!(metadata.track, Some(7));
+        assert_eq!(metadata.genre.as_deref(), Some("Rock"));
+    }
+
+    #[test]
+    fn no_tag() {
+        assert_eq!(Id3V1::read(&mut Cursor::new(vec![0xFF; 256])), None);
+    }
+}
@@ -1,20 +1,25 @@
+use std::io::{Read, Seek, SeekFrom};
+
 use bytes::{Buf, Bytes};
+use jukebox_decoder::Metadata;
+
+use super::id3_v1::{GENRES, latin1, number};
 
 pub(crate) struct Id3V2 {
     // data: Bytes,
 }
 
 const HEADER_SIZE: usize = 10;
+/// Largest frame read for its tags, bigger ones like pictures are skipped
+const MAX_FRAME_SIZE: usize = 64 * 1024;
 
 impl TryFrom<&mut Bytes> for Id3V2 {
     type Error = jukebox_decoder::Error;
     fn try_from(value: &mut Bytes) -> Result<Self, Self::Error> {
         match value.chunk() {
             &[b'I', b'D', b'3', _v1, _v2, _flag, s1, s2, s3, s4, ..] => {
-                let size =
-                    ((s1 as u32) << 21) | ((s2 as u32) << 14) | ((s3 as u32) << 7) | (s4 as u32);
-                let size = size as usize;
-                if size > value.len() {
+                let size = synchsafe([s1, s2, s3, s4]);
+                if size + HEADER_SIZE > value.len() {
                     Err(Self::Error::InvalidData)
                 } else {
                     value.advance(size + HEADER_SIZE);
@@ -25,3 +30,227 @@ impl TryFrom<&mut Bytes> for Id3V2 {
         }
     }
 }
+
+impl Id3V2 {
+    /// Tags of the ID3v2 tag at the start of a file.
+    ///
+    /// The sizes come from the file, so the tag is read frame by frame and
+    /// the frames too big to hold tags are skipped rather than loaded.
+    pub(super) fn read<R: Read + Seek>(reader: &mut R) -> Option<Metadata> {
+        let mut header = [0u8; HEADER_SIZE];
+        reader.seek(SeekFrom::Start(0)).ok()?;
+        reader.read_exact(&mut header).ok()?;
+
+        let (version, flags, mut remaining) = match header {
+            [b'I', b'D', b'3', version, _revision, flags, s1, s2, s3, s4] => {
+                (version, flags, synchsafe([s1, s2, s3, s4]))
+            }
+            _ => return None,
+        };
+        let (id_size, header_size) = match version {
+            2 => (3, 6),
+            3 | 4 => (4, 10),
+            _ => return None,
+        };
+
+        if flags & 0x40 != 0 && version != 2 {
+            // Skip extended header
+            let mut size = [0u8; 4];
+            reader.read_exact(&mut size).ok()?;
+            let size = match version {
+                3 => u32::from_be_bytes(size) as usize + 4,
+                _ => synchsafe(size),
+            };
+            remaining = remaining.checked_sub(size)?;
+            reader.seek(SeekFrom::Current(size as i64 - 4)).ok()?;
+        }
+
+        let mut metadata = Metadata::default();
+        let mut header = [0u8; HEADER_SIZE];
+        while remaining >= header_size {
+            let header = &mut header[..header_size];
+            if reader.read_exact(header).is_err() {
+                break;
+            }
+            remaining -= header_size;
+
+            let (id, size) = header.split_at(id_size);
+            if id.iter().all(|&c| c == 0) {
+                // Padding
+                break;
+            }
+            let size = match (version, size) {
+                (2, &[s1, s2, s3]) => u32::from_be_bytes([0, s1, s2, s3]) as usize,
+                (3, &[s1, s2, s3, s4, ..]) => u32::from_be_bytes([s1, s2, s3, s4]) as usize,
+                (_, &[s1, s2, s3, s4, ..]) => synchsafe([s1, s2, s3, s4]),
+                _ => break,
+            };
+            if size > remaining {
+                break;
+            }
+            remaining -= size;
+
+            if size > MAX_FRAME_SIZE {
+                if reader.seek(SeekFrom::Current(size as i64)).is_err() {
+                    break;
+                }
+                continue;
+            }
+            let mut content = vec![0u8; size];
+            if reader.read_exact(&mut content).is_err() {
+                break;
+            }
+            tag(&mut metadata, id, &content);
+        }
+        Some(metadata)
+    }
+}
+
+/// Fill the tag held by a frame.
+fn tag(metadata: &mut Metadata, id: &[u8], content: &[u8]) {
+    if id == b"POPM" || id == b"POP" {
+        metadata.rating = popularimeter(content);
+        return;
+    }
+    let Some(value) = text(content) else {
+        return;
+    };
+    match id {
+        b"TIT2" | b"TT2" => metadata.title = Some(value),
+        b"TPE1" | b"TP1" => metadata.artist = Some(value),
+        b"TALB" | b"TAL" => metadata.album = Some(value),
+        b"TCON" | b"TCO" => metadata.genre = Some(genre(&value)),
+        b"TYER" | b"TYE" | b"TDRC" => metadata.year = number(&value),
+        b"TPOS" | b"TPA" => metadata.disc = number(&value),
+        b"TRCK" | b"TRK" => metadata.track = number(&value),
+        _ => {}
+    }
+}
+
+/// Convert the popularimeter rating byte to stars, as most players do.
+fn popularimeter(content: &[u8]) -> Option<u8> {
+    let email = content.iter().position(|&c| c == 0)?;
+    match *content.get(email + 1)? {
+        0 => None,
+        1..=31 => Some(1),
+        32..=95 => Some(2),
+        96..=159 => Some(3),
+        160..=223 => Some(4),
+        _ => Some(5),
+    }
+}
+
+fn synchsafe(size: [u8; 4]) -> usize {
+    size.iter()
+        .fold(0, |acc, &b| (acc << 7) | (b & 0x7F) as usize)
+}
+
+/// Decode a text frame, only the first value is kept.
+fn text(content: &[u8]) -> Option<String> {
+    let (&encoding, data) = content.split_first()?;
+    let value = match encoding {
+        0 => latin1(data)?,
+        1 => match data {
+            [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+            [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+            _ => utf16(data, u16::from_le_bytes),
+        },
+        2 => utf16(data, u16::from_be_bytes),
+        3 => String::from_utf8_lossy(data.split(|&c| c == 0).next()?).to_string(),
+        _ => return None,
+    };
+    let value = value.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+fn utf16(data: &[u8], decode: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|c| decode([c[0], c[1]]))
+        .take_while(|&c| c != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Resolve ID3v1 genre references like `(8)` or `8`.
+fn genre(value: &str) -> String {
+    let reference = value
+        .strip_prefix('(')
+        .and_then(|v| v.split_once(')'))
+        .map(|(idx, rest)| (idx, rest.trim()))
+        .unwrap_or((value, ""));
+    match (reference.0.parse::<usize>(), reference.1) {
+        (Ok(idx), "") => GENRES
+            .get(idx)
+            .map(|g| g.to_string())
+            .unwrap_or(value.to_string()),
+        (Ok(_), rest) => rest.to_string(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn frame(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend_from_slice(&(content.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0]);
+        frame.extend_from_slice(content);
+        frame
+    }
+
+    /// ID3v2.3 tag of `frames`, followed by `padding` zero bytes.
+    fn tag(frames: &[Vec<u8>], padding: usize) -> Vec<u8> {
+        let size = frames.iter().map(Vec::len).sum::<usize>() + padding;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend((0..4).rev().map(|i| ((size >> (7 * i)) & 0x7F) as u8));
+        frames.iter().for_each(|frame| tag.extend_from_slice(frame));
+        tag.resize(tag.len() + padding, 0);
+        tag
+    }
+
+    #[test]
+    fn read_text_and_rating() {
+        let artist: Vec<u8> = [1, 0xFF, 0xFE]
+            .into_iter()
+            .chain("Ästhet".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let data = tag(
+            &[
+                frame(b"TIT2", b"\x00Title\x00"),
+                frame(b"TPE1", &artist),
+                frame(b"APIC", &vec![0; MAX_FRAME_SIZE + 1]),
+                frame(b"TCON", b"\x00(8)"),
+                frame(b"TRCK", b"\x033/12"),
+                frame(b"POPM", b"me@example.com\x00\xC4\x00\x00\x00\x00"),
+            ],
+            16,
+        );
+        let metadata = Id3V2::read(&mut Cursor::new(data)).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.artist.as_deref(), Some("Ästhet"));
+        assert_eq!(metadata.genre.as_deref(), Some("Jazz"));
+        assert_eq!(metadata.track, Some(3));
+        assert_eq!(metadata.rating, Some(4));
+    }
+
+    #[test]
+    fn oversized_tag_is_not_loaded() {
+        // The header claims 256 MiB while the file ends after the first frame
+        let mut data = b"ID3\x04\x00\x00\x7F\x7F\x7F\x7F".to_vec();
+        data.extend(frame(b"TIT2", b"\x03Title"));
+        let metadata = Id3V2::read(&mut Cursor::new(data)).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+    }
+
+    #[test]
+    fn truncated_tag_is_invalid() {
+        let mut data = Bytes::from(tag(&[frame(b"TIT2", b"\x00Title")], 0));
+        data.truncate(data.len() - 1);
+        assert!(Id3V2::try_from(&mut data).is_err());
+    }
+}
@@ -1,12 +1,27 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    io::{Read, Seek},
+};
 
 use bytes::{Buf, Bytes};
 
-use jukebox_decoder::Frame as DecoderFrame;
+use jukebox_decoder::{Frame as DecoderFrame, Metadata};
 
 mod id3_v1;
 mod id3_v2;
 mod mp3;
+mod xing;
+
+pub(crate) use xing::Xing;
+
+/// Read ID3v2 tags at the start of the file, completed by ID3v1 tags at its end.
+pub(crate) fn metadata<R: Read + Seek>(reader: &mut R) -> Metadata {
+    let mut metadata = id3_v2::Id3V2::read(reader).unwrap_or_default();
+    if let Some(v1) = id3_v1::Id3V1::read(reader) {
+        metadata.merge(v1);
+    }
+    metadata
+}
 
 pub(crate) enum Frame {
     Id3V1(id3_v1::Id3V1),
@@ -28,6 +43,22 @@ impl Frame {
         }
     }
 
+    /// Drop the next audio frame, only reading its header, and return its
+    /// number of samples and sample rate.
+    pub(super) fn skip(data: &mut Bytes) -> Result<Option<(usize, usize)>, jukebox_decoder::Error> {
+        loop {
+            if let Some(header) = mp3::Header::parse(data.chunk()) {
+                data.advance(header.size.min(data.len()));
+                return Ok(Some((header.nb_samples, header.sample_rate)));
+            }
+            // Tags are parsed to find where they end
+            if let Frame::Mp3(frame) = Self::decode_one_frame(data)? {
+                let frame = DecoderFrame::from(frame);
+                return Ok(Some((frame.nb_samples, frame.sample_rate)));
+            }
+        }
+    }
+
     fn decode_one_frame(data: &mut Bytes) -> Result<Frame, jukebox_decoder::Error> {
         match data.chunk() {
             [b'T', b'A', b'G', ..] => id3_v1::Id3V1::try_from(data).map(Frame::Id3V1),
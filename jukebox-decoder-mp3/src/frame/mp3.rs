@@ -11,11 +11,17 @@ impl From<Mp3Frame> for Frame {
     }
 }
 
-impl TryFrom<&mut Bytes> for Mp3Frame {
-    type Error = jukebox_decoder::Error;
+/// What a frame header tells to walk the frames without reading them.
+pub(crate) struct Header {
+    /// Size of the frame, header included
+    pub(crate) size: usize,
+    pub(crate) nb_samples: usize,
+    pub(crate) sample_rate: usize,
+}
 
-    fn try_from(value: &mut Bytes) -> Result<Self, Self::Error> {
-        match value.chunk() {
+impl Header {
+    pub(crate) fn parse(data: &[u8]) -> Option<Self> {
+        match data {
             &[0xFF, h1, h2, _, ..] if h1 & 0xE0 == 0xE0 => {
                 let (bitrate_band_idx, sampling_shift, nb_frames, padding_bytes) =
                     match ((h1 >> 3) & 0x03, ((h1 >> 1) & 0x03)) {
@@ -71,15 +77,28 @@ impl TryFrom<&mut Bytes> for Mp3Frame {
                     // Check padding bit
                     size += padding_bytes;
                 }
-                Ok(Mp3Frame {
-                    data: Frame::new(
-                        value.split_to(size as usize),
-                        nb_frames as usize,
-                        sampling_rate as usize,
-                    ),
+                Some(Header {
+                    size: size as usize,
+                    nb_samples: nb_frames as usize,
+                    sample_rate: sampling_rate as usize,
                 })
             }
-            _ => Err(Self::Error::InvalidData),
+            _ => None,
         }
     }
 }
+
+impl TryFrom<&mut Bytes> for Mp3Frame {
+    type Error = jukebox_decoder::Error;
+
+    fn try_from(value: &mut Bytes) -> Result<Self, Self::Error> {
+        let header = Header::parse(value.chunk()).ok_or(Self::Error::InvalidData)?;
+        Ok(Mp3Frame {
+            data: Frame::new(
+                value.split_to(header.size),
+                header.nb_samples,
+                header.sample_rate,
+            ),
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use jukebox_decoder::Frame;
+
+/// Gapless information from the Xing/Info header written by LAME in place of
+/// the first audio frame.
+pub(crate) struct Xing {
+    /// Number of audio frames, the Xing frame excluded
+    pub(crate) frames: Option<usize>,
+    /// Encoder delay in samples
+    pub(crate) delay: usize,
+    /// Encoder padding in samples
+    pub(crate) padding: usize,
+}
+
+const FRAMES_FLAG: u32 = 0x01;
+const BYTES_FLAG: u32 = 0x02;
+const TOC_FLAG: u32 = 0x04;
+const QUALITY_FLAG: u32 = 0x08;
+
+/// Offset of the delay and padding fields from the start of the LAME tag
+const LAME_DELAY_OFFSET: usize = 21;
+/// Samples added by the decoder on top of the encoder delay
+const DECODER_DELAY: usize = 529;
+
+impl Xing {
+    /// Number of frames to drop at the start and at the end of the stream.
+    ///
+    /// Frames can't be cut without decoding them, the delay and the padding
+    /// are rounded to the nearest frame.
+    pub(crate) fn trimmed(&self, frame_samples: usize) -> (usize, usize) {
+        let frames = |samples: usize| (samples + frame_samples / 2) / frame_samples.max(1);
+        (
+            frames(self.delay + DECODER_DELAY),
+            frames(self.padding.saturating_sub(DECODER_DELAY)),
+        )
+    }
+}
+
+impl TryFrom<&Frame> for Xing {
+    type Error = jukebox_decoder::Error;
+
+    fn try_from(frame: &Frame) -> Result<Self, Self::Error> {
+        let data = frame.data.as_ref();
+        let (h1, h3) = match data {
+            &[0xFF, h1, _, h3, ..] => (h1, h3),
+            _ => return Err(Self::Error::InvalidData),
+        };
+
+        let mono = (h3 >> 6) == 0x03;
+        let side_info = match ((h1 >> 3) & 0x03, mono) {
+            (3, false) => 32, // Mpeg 1
+            (3, true) => 17,
+            (_, false) => 17, // Mpeg 2 and 2.5
+            (_, true) => 9,
+        };
+
+        let mut offset = 4 + side_info;
+        match data.get(offset..offset + 4) {
+            Some(b"Xing") | Some(b"Info") => offset += 4,
+            _ => return Err(Self::Error::InvalidData),
+        }
+
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|v| u32::from_be_bytes([v[0], v[1], v[2], v[3]]))
+                .ok_or(Self::Error::InvalidData)
+        };
+
+        let flags = read_u32(offset)?;
+        offset += 4;
+
+        let mut frames = None;
+        if flags & FRAMES_FLAG != 0 {
+            frames = Some(read_u32(offset)? as usize);
+            offset += 4;
+        }
+        if flags & BYTES_FLAG != 0 {
+            offset += 4;
+        }
+        if flags & TOC_FLAG != 0 {
+            offset += 100;
+        }
+        if flags & QUALITY_FLAG != 0 {
+            offset += 4;
+        }
+
+        let (delay, padding) =
+            match data.get(offset + LAME_DELAY_OFFSET..offset + LAME_DELAY_OFFSET + 3) {
+                Some(&[d1, d2, d3]) if data[offset..].starts_with(b"LAME") => (
+                    ((d1 as usize) << 4) | ((d2 as usize) >> 4),
+                    (((d2 as usize) & 0x0F) << 8) | (d3 as usize),
+                ),
+                _ => (0, 0),
+            };
+
+        Ok(Self {
+            frames,
+            delay,
+            padding,
+        })
+    }
+}
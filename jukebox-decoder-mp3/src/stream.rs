@@ -1,24 +1,193 @@
 use bytes::Bytes;
 
-use jukebox_decoder::Stream;
+use jukebox_decoder::{Format, Frame, Stream};
+
+use super::frame::Xing;
 
 #[derive(Default)]
 pub struct Mp3Stream {
     data: Bytes,
+    /// Audio frames already decoded, the Xing frame excluded
+    index: usize,
+    started: bool,
+    gapless: Option<Trim>,
 }
 
-impl Stream for Mp3Stream {}
+/// Frames only holding encoder delay or padding.
+#[derive(Clone, Copy)]
+struct Trim {
+    /// Number of audio frames, when known
+    frames: Option<usize>,
+    start: usize,
+    end: usize,
+}
+
+impl Stream for Mp3Stream {
+    fn format(&self) -> Option<Format> {
+        Some(Format::MP3)
+    }
+
+    fn skip_frame(&mut self) -> Option<(usize, usize)> {
+        if !self.started {
+            // The first frame may hold the gapless information
+            return self
+                .next()
+                .map(|frame| (frame.nb_samples, frame.sample_rate));
+        }
+        loop {
+            let skipped = super::frame::Frame::skip(&mut self.data).unwrap_or_default()?;
+            self.index += 1;
+            if !self.is_silent(self.index - 1) {
+                return Some(skipped);
+            }
+        }
+    }
+}
 
 impl Mp3Stream {
     pub(super) fn new(buf: Bytes) -> Self {
-        Self { data: buf }
+        Self {
+            data: buf,
+            ..Default::default()
+        }
+    }
+
+    fn decode(&mut self) -> Option<Frame> {
+        super::frame::Frame::decoder(&mut self.data).unwrap_or_default()
+    }
+
+    /// Check if the frame at `index` only holds encoder delay or padding
+    fn is_silent(&self, index: usize) -> bool {
+        self.gapless.is_some_and(|trim| {
+            index < trim.start || trim.frames.is_some_and(|frames| index + trim.end >= frames)
+        })
     }
 }
 
 impl Iterator for Mp3Stream {
-    type Item = jukebox_decoder::Frame;
+    type Item = Frame;
 
     fn next(&mut self) -> Option<Self::Item> {
-        super::frame::Frame::decoder(&mut self.data).unwrap_or_default()
+        if !self.started {
+            self.started = true;
+            let frame = self.decode()?;
+            match Xing::try_from(&frame) {
+                // Not an audio frame, players would output it as silence
+                Ok(xing) => {
+                    let (start, end) = xing.trimmed(frame.nb_samples);
+                    self.gapless = Some(Trim {
+                        frames: xing.frames,
+                        start,
+                        end,
+                    });
+                }
+                Err(_) => {
+                    self.index += 1;
+                    return Some(frame);
+                }
+            }
+        }
+
+        loop {
+            let frame = self.decode()?;
+            self.index += 1;
+            if !self.is_silent(self.index - 1) {
+                return Some(frame);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// MPEG 1 layer III, 128 kbit/s, 44.1 kHz, joint stereo: 417 bytes
+    const HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x64];
+    const FRAME_SIZE: usize = 417;
+
+    fn audio_frame(tag: u8) -> Vec<u8> {
+        let mut frame = vec![tag; FRAME_SIZE];
+        frame[..4].copy_from_slice(&HEADER);
+        frame
+    }
+
+    /// Info frame with a frame count and a LAME tag.
+    fn xing_frame(frames: u32, delay: u16, padding: u16) -> Vec<u8> {
+        let mut frame = vec![0; FRAME_SIZE];
+        frame[..4].copy_from_slice(&HEADER);
+        frame[36..40].copy_from_slice(b"Info");
+        frame[40..44].copy_from_slice(&1u32.to_be_bytes());
+        frame[44..48].copy_from_slice(&frames.to_be_bytes());
+        frame[48..57].copy_from_slice(b"LAME3.100");
+        frame[69] = (delay >> 4) as u8;
+        frame[70] = ((delay & 0x0F) << 4) as u8 | (padding >> 8) as u8;
+        frame[71] = padding as u8;
+        frame
+    }
+
+    fn stream(frames: &[Vec<u8>]) -> Mp3Stream {
+        Mp3Stream::new(Bytes::from(frames.concat()))
+    }
+
+    #[test]
+    fn parse_lame_tag() {
+        let data = Bytes::from(xing_frame(10, 576, 1800));
+        let frame = Frame::new(data, 1152, 44100);
+        let xing = Xing::try_from(&frame).unwrap();
+        assert_eq!(xing.frames, Some(10));
+        assert_eq!(xing.delay, 576);
+        assert_eq!(xing.padding, 1800);
+    }
+
+    #[test]
+    fn audio_frame_is_not_xing() {
+        let frame = Frame::new(Bytes::from(audio_frame(1)), 1152, 44100);
+        assert!(Xing::try_from(&frame).is_err());
+    }
+
+    #[test]
+    fn trim_rounds_to_frames() {
+        let xing = |delay, padding| Xing {
+            frames: None,
+            delay,
+            padding,
+        };
+        // Usual LAME delay, 576 + 529 samples, is most of a frame
+        assert_eq!(xing(576, 1000).trimmed(1152), (1, 0));
+        assert_eq!(xing(576, 1800).trimmed(1152), (1, 1));
+        assert_eq!(xing(0, 0).trimmed(1152), (0, 0));
+        assert_eq!(xing(1700, 2900).trimmed(1152), (2, 2));
+    }
+
+    #[test]
+    fn gapless_frames_dropped() {
+        let mut frames = vec![xing_frame(10, 576, 1800)];
+        frames.extend((0..10).map(audio_frame));
+        let tags: Vec<u8> = stream(&frames).map(|frame| frame.data[4]).collect();
+        assert_eq!(tags, (1..9).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn without_xing_every_frame_played() {
+        let frames: Vec<_> = (0..5).map(audio_frame).collect();
+        let stream = stream(&frames);
+        let frames: Vec<_> = stream.collect();
+        assert_eq!(frames.len(), 5);
+        assert!(frames.iter().all(|frame| frame.nb_samples == 1152));
+        assert!(frames.iter().all(|frame| frame.sample_rate == 44100));
+    }
+
+    #[test]
+    fn skip_frames_like_next() {
+        let mut frames = vec![xing_frame(10, 576, 1800)];
+        frames.extend((0..10).map(audio_frame));
+        let mut stream = stream(&frames);
+        for _ in 0..3 {
+            assert_eq!(stream.skip_frame(), Some((1152, 44100)));
+        }
+        assert_eq!(stream.next().map(|frame| frame.data[4]), Some(4));
+        let skipped = std::iter::from_fn(|| stream.skip_frame()).count();
+        assert_eq!(skipped, 4);
     }
 }
@@ -0,0 +1,15 @@
+use crate::{Frame, Stream};
+
+/// A stream without any frame.
+#[derive(Debug, Default)]
+pub struct Empty;
+
+impl Iterator for Empty {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        None
+    }
+}
+
+impl Stream for Empty {}
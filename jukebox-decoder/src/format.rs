@@ -0,0 +1,24 @@
+/// How the frames of a stream are wrapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Frames follow each other without container, like MP3 or ADTS.
+    Raw,
+    Ogg,
+    Mp4,
+}
+
+/// Audio format produced by a decoder, frames of the same format can be
+/// concatenated into a single stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    /// MIME type of the stream, like `audio/mpeg`
+    pub mime: &'static str,
+    pub container: Container,
+}
+
+impl Format {
+    pub const MP3: Format = Format {
+        mime: "audio/mpeg",
+        container: Container::Raw,
+    };
+}
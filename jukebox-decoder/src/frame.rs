@@ -1,6 +1,8 @@
+use std::time::Duration;
+
 use bytes::Bytes;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Frame {
     pub data: Bytes,
     pub nb_samples: usize,
@@ -21,4 +23,12 @@ impl Frame {
             sample_rate,
         }
     }
+
+    /// Playing time of the frame, zero when the sample rate is unknown.
+    pub fn duration(&self) -> Duration {
+        match self.sample_rate {
+            0 => Duration::ZERO,
+            rate => Duration::from_micros((self.nb_samples * 1_000_000 / rate) as u64),
+        }
+    }
 }
@@ -6,12 +6,14 @@
 //!
 //! - `Stream`: A trait representing a stream of frames.
 //! - `Decoder`: A trait representing a decoder that can decode a buffer of bytes into a stream of frames.
+//! - `Format`: The MIME type and container of the frames produced by a decoder.
+//! - `Metadata`: Tags read by a decoder from an audio file.
 //!
 //! ## Example
 //!
 //! ```rust
 //! use bytes::Bytes;
-//! use jukebox_decoder::{Decoder, Frame, Stream};
+//! use jukebox_decoder::{Decoder, Format, Frame, Stream};
 //!
 //! struct MyDecoder;
 //!
@@ -20,6 +22,10 @@
 //!         "MyDecoder"
 //!     }
 //!
+//!     fn format() -> Format {
+//!         Format::MP3
+//!     }
+//!
 //!     fn decode(buf: Bytes) -> Box<dyn Stream> {
 //!         // Implementation goes here
 //!         Box::new(MyStream { data: buf.clone() })
@@ -44,19 +50,46 @@
 //! impl Stream for MyStream {}
 //! ```
 
+use std::io::{Read, Seek};
+
 use bytes::Bytes;
 
+mod empty;
 mod error;
+mod format;
 mod frame;
+mod metadata;
 
+pub use empty::Empty;
 pub use error::Error;
+pub use format::{Container, Format};
 pub use frame::Frame;
+pub use metadata::Metadata;
 
 /// A trait representing a stream of frames.
-pub trait Stream: Iterator<Item = Frame> + Sync + Send {}
+pub trait Stream: Iterator<Item = Frame> + Sync + Send {
+    /// Format of the frames, `None` when the stream has no frame to tell.
+    fn format(&self) -> Option<Format> {
+        None
+    }
+
+    /// Drop the next frame without producing it, to keep up with time while
+    /// nobody listens. Returns its number of samples and its sample rate,
+    /// `None` at the end of the stream.
+    fn skip_frame(&mut self) -> Option<(usize, usize)> {
+        self.next()
+            .map(|frame| (frame.nb_samples, frame.sample_rate))
+    }
+}
 
 /// A trait representing a decoder that can decode a buffer of bytes into a stream of frames.
 pub trait Decoder {
     fn name() -> &'static str;
+    /// Format of every stream returned by [`Decoder::decode`].
+    fn format() -> Format;
     fn decode(buf: Bytes) -> Box<dyn Stream>;
+    /// Read the tags of a file, only the needed parts are read.
+    fn metadata<R: Read + Seek>(_reader: &mut R) -> Metadata {
+        Metadata::default()
+    }
 }
@@ -0,0 +1,27 @@
+/// Tags read from an audio file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    pub year: Option<u16>,
+    pub disc: Option<u32>,
+    pub track: Option<u32>,
+    /// Rating from 1 to 5 stars
+    pub rating: Option<u8>,
+}
+
+impl Metadata {
+    /// Fill the missing tags from `other`.
+    pub fn merge(&mut self, other: Metadata) {
+        self.title = self.title.take().or(other.title);
+        self.artist = self.artist.take().or(other.artist);
+        self.album = self.album.take().or(other.album);
+        self.genre = self.genre.take().or(other.genre);
+        self.year = self.year.or(other.year);
+        self.disc = self.disc.or(other.disc);
+        self.track = self.track.or(other.track);
+        self.rating = self.rating.or(other.rating);
+    }
+}
@@ -44,6 +44,6 @@ where
     D: Decoder,
 {
     fn add_assign(&mut self, directory: P) {
-        let _ = self.inner.add(directory);
+        let _ = self.inner.add::<D>(directory);
     }
 }
@@ -1,16 +1,26 @@
-use std::{io, ops::Deref, path::Path, sync::Arc};
+use std::{
+    io,
+    ops::Deref,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
 
 use bytes::Bytes;
 
-use jukebox_decoder::Decoder;
-use jukebox_library::{Library, LibraryId, Stream};
+use jukebox_decoder::{Decoder, Format};
+use jukebox_library::{Library, LibraryId, Resource, Song, Stats, Stream};
 use rand::Rng;
+use tracing::warn;
 
 use crate::Builder;
 
 #[derive(Debug, Default)]
 pub struct LibraryFileInner {
-    files: Vec<String>,
+    files: Vec<Song>,
+    /// Kept in memory only, lost on restart or when the library is scanned
+    /// again, ratings start from the tags of the files
+    stats: Mutex<Vec<Stats>>,
 }
 
 #[derive(Debug)]
@@ -34,33 +44,109 @@ where
 }
 
 impl LibraryFileInner {
-    pub(crate) fn add(&mut self, directory: impl AsRef<Path>) -> Result<(), io::Error> {
+    pub(crate) fn add<D: Decoder>(&mut self, directory: impl AsRef<Path>) -> Result<(), io::Error> {
         std::fs::read_dir(directory)?
             .filter_map(Result::ok)
             .filter(|file| file.metadata().map(|m| m.is_file()).unwrap_or(false))
             .for_each(|file| {
-                self.files.push(file.path().to_string_lossy().to_string());
+                let metadata = std::fs::File::open(file.path())
+                    .map(|mut f| D::metadata(&mut f))
+                    .unwrap_or_default();
+                let stats = Stats {
+                    rating: metadata.rating,
+                    ..Default::default()
+                };
+                self.stats.get_mut().unwrap().push(stats.clone());
+                self.files.push(Song {
+                    id: self.files.len(),
+                    path: file.path().to_string_lossy().to_string(),
+                    metadata,
+                    stats,
+                });
             });
         Ok(())
     }
 }
 
+impl<D> LibraryFile<D>
+where
+    D: Decoder,
+{
+    /// Format of the songs, every file is read by the same decoder.
+    pub fn format(&self) -> Format {
+        D::format()
+    }
+
+    /// Whether no song was found in the paths of the library.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Decode a song, `None` if its file can't be read anymore.
+    async fn load(&self, song: &Song) -> Option<Box<dyn Stream>> {
+        match tokio::fs::read(&song.path).await {
+            Ok(data) => Some(D::decode(Bytes::from(data))),
+            Err(e) => {
+                warn!("library: can't read {}: {}", song.path, e);
+                None
+            }
+        }
+    }
+}
+
 impl<D> Library for LibraryFile<D>
 where
     D: Decoder,
 {
-    async fn random(&self) -> (LibraryId, Box<dyn Stream>) {
+    async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+        if self.files.is_empty() {
+            return None;
+        }
         let index = rand::rng().random_range(0..self.files.len());
-        let filename = &self.files[index];
-        (
-            index,
-            D::decode(Bytes::from(tokio::fs::read(filename).await.unwrap())),
-        )
+        Some((index, self.load(&self.files[index]).await?))
     }
 
     async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
-        let file = self.files.get(id)?;
-        Some(D::decode(Bytes::from(tokio::fs::read(file).await.unwrap())))
+        self.load(self.files.get(id)?).await
+    }
+
+    async fn ids(&self) -> Vec<LibraryId> {
+        (0..self.files.len()).collect()
+    }
+
+    async fn song(&self, id: LibraryId) -> Option<Song> {
+        let mut song = self.files.get(id).cloned()?;
+        song.stats = self.stats.lock().unwrap()[id].clone();
+        Some(song)
+    }
+
+    async fn resource(&self, id: LibraryId) -> Option<Resource> {
+        let song = self.files.get(id)?;
+        let file = tokio::fs::File::open(&song.path).await.ok()?;
+        let metadata = file.metadata().await.ok()?;
+        Some(Resource {
+            body: Box::new(file),
+            length: metadata.len(),
+            modified: metadata.modified().ok(),
+            format: D::format(),
+        })
+    }
+
+    async fn played(&self, id: LibraryId) {
+        if let Some(stats) = self.stats.lock().unwrap().get_mut(id) {
+            stats.plays += 1;
+            stats.last_played = Some(SystemTime::now());
+        }
+    }
+
+    async fn rate(&self, id: LibraryId, rating: Option<u8>) -> bool {
+        match self.stats.lock().unwrap().get_mut(id) {
+            Some(stats) => {
+                stats.rating = rating.map(|r| r.clamp(1, 5));
+                true
+            }
+            None => false,
+        }
     }
 }
 
@@ -0,0 +1,83 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use crate::{Library, LibraryId, Resource, Song, Stream};
+
+/// A future boxed to be returned by an object safe trait.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Object safe version of [`Library`], implemented by every library.
+///
+/// `Arc<dyn DynLibrary>` implements [`Library`] again, so it can be given to
+/// any playlist.
+pub trait DynLibrary: Send + Sync {
+    fn random(&self) -> BoxFuture<'_, Option<(LibraryId, Box<dyn Stream>)>>;
+    fn get(&self, id: LibraryId) -> BoxFuture<'_, Option<Box<dyn Stream>>>;
+    fn ids(&self) -> BoxFuture<'_, Vec<LibraryId>>;
+    fn song(&self, id: LibraryId) -> BoxFuture<'_, Option<Song>>;
+    fn resource(&self, id: LibraryId) -> BoxFuture<'_, Option<Resource>>;
+    fn played(&self, id: LibraryId) -> BoxFuture<'_, ()>;
+    fn rate(&self, id: LibraryId, rating: Option<u8>) -> BoxFuture<'_, bool>;
+}
+
+impl<T> DynLibrary for T
+where
+    T: Library,
+{
+    fn random(&self) -> BoxFuture<'_, Option<(LibraryId, Box<dyn Stream>)>> {
+        Box::pin(Library::random(self))
+    }
+
+    fn get(&self, id: LibraryId) -> BoxFuture<'_, Option<Box<dyn Stream>>> {
+        Box::pin(Library::get(self, id))
+    }
+
+    fn ids(&self) -> BoxFuture<'_, Vec<LibraryId>> {
+        Box::pin(Library::ids(self))
+    }
+
+    fn song(&self, id: LibraryId) -> BoxFuture<'_, Option<Song>> {
+        Box::pin(Library::song(self, id))
+    }
+
+    fn resource(&self, id: LibraryId) -> BoxFuture<'_, Option<Resource>> {
+        Box::pin(Library::resource(self, id))
+    }
+
+    fn played(&self, id: LibraryId) -> BoxFuture<'_, ()> {
+        Box::pin(Library::played(self, id))
+    }
+
+    fn rate(&self, id: LibraryId, rating: Option<u8>) -> BoxFuture<'_, bool> {
+        Box::pin(Library::rate(self, id, rating))
+    }
+}
+
+impl<'a> Library for Arc<dyn DynLibrary + 'a> {
+    async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+        DynLibrary::random(self.as_ref()).await
+    }
+
+    async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
+        DynLibrary::get(self.as_ref(), id).await
+    }
+
+    async fn ids(&self) -> Vec<LibraryId> {
+        DynLibrary::ids(self.as_ref()).await
+    }
+
+    async fn song(&self, id: LibraryId) -> Option<Song> {
+        DynLibrary::song(self.as_ref(), id).await
+    }
+
+    async fn resource(&self, id: LibraryId) -> Option<Resource> {
+        DynLibrary::resource(self.as_ref(), id).await
+    }
+
+    async fn played(&self, id: LibraryId) {
+        DynLibrary::played(self.as_ref(), id).await
+    }
+
+    async fn rate(&self, id: LibraryId, rating: Option<u8>) -> bool {
+        DynLibrary::rate(self.as_ref(), id, rating).await
+    }
+}
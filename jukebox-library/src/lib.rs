@@ -1,13 +1,113 @@
-#![allow(async_fn_in_trait)]
+use std::{future::Future, path::Path, sync::Arc, time::SystemTime};
 
-pub use jukebox_decoder::Stream;
+use jukebox_decoder::Format;
+use tokio::io::{AsyncRead, AsyncSeek};
+
+pub use jukebox_decoder::{Metadata, Stream};
+
+mod boxed;
+
+pub use boxed::{BoxFuture, DynLibrary};
 
 pub type LibraryId = usize;
 
-pub trait Library: Send + Clone {
-    async fn random(&self) -> (LibraryId, Box<dyn Stream>);
-    async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>>;
+/// A song of a library with its tags.
+#[derive(Debug, Clone)]
+pub struct Song {
+    pub id: LibraryId,
+    pub path: String,
+    pub metadata: Metadata,
+    pub stats: Stats,
+}
+
+impl Song {
+    /// `Artist - Title` as shown by players, the file name without tags.
+    pub fn title(&self) -> String {
+        match (&self.metadata.artist, &self.metadata.title) {
+            (Some(artist), Some(title)) => format!("{artist} - {title}"),
+            (None, Some(title)) => title.clone(),
+            _ => Path::new(&self.path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// `title` on a single line, a line break in a tag would start another
+    /// entry of a line based playlist.
+    pub fn title_line(&self) -> String {
+        self.title().replace(['\r', '\n'], " ")
+    }
+}
+
+/// Listening statistics of a song, libraries may not keep them across restarts.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Rating from 1 to 5 stars
+    pub rating: Option<u8>,
+    pub plays: u64,
+    pub last_played: Option<SystemTime>,
+}
+
+/// Readable and seekable content, whatever the storage behind it.
+pub trait Body: AsyncRead + AsyncSeek + Send + Unpin {}
+
+impl<T> Body for T where T: AsyncRead + AsyncSeek + Send + Unpin {}
+
+/// Original content of a song, as kept by the storage of the library.
+///
+/// The body is opened but not read, to serve only the requested part of it.
+pub struct Resource {
+    pub body: Box<dyn Body>,
+    pub length: u64,
+    pub modified: Option<SystemTime>,
+    /// Format of the decoder reading the song
+    pub format: Format,
+}
+
+impl std::fmt::Debug for Resource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resource")
+            .field("length", &self.length)
+            .field("modified", &self.modified)
+            .field("format", &self.format)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Implementations can use `async fn`, the futures must be `Send`.
+pub trait Library: Send + Sync + Clone {
+    /// A random song, `None` if the library is empty.
+    fn random(&self) -> impl Future<Output = Option<(LibraryId, Box<dyn Stream>)>> + Send;
+    fn get(&self, id: LibraryId) -> impl Future<Output = Option<Box<dyn Stream>>> + Send;
+    /// Identifiers of every song currently available.
+    fn ids(&self) -> impl Future<Output = Vec<LibraryId>> + Send;
+    fn song(&self, id: LibraryId) -> impl Future<Output = Option<Song>> + Send;
+    /// Undecoded content of a song, to serve it as is. None by default, the
+    /// library can't give its songs.
+    fn resource(&self, id: LibraryId) -> impl Future<Output = Option<Resource>> + Send {
+        let _ = id;
+        async { None }
+    }
+    /// Record that a song started playing. Nothing is recorded by default.
+    fn played(&self, id: LibraryId) -> impl Future<Output = ()> + Send {
+        let _ = id;
+        async {}
+    }
+    /// Set or clear the rating of a song, returns `false` if the song is
+    /// unknown or, by default, if the library doesn't keep ratings.
+    fn rate(&self, id: LibraryId, rating: Option<u8>) -> impl Future<Output = bool> + Send {
+        let _ = (id, rating);
+        async { false }
+    }
+
+    /// Erase the type of the library, to select it at runtime.
+    fn shared(self) -> Arc<dyn DynLibrary>
+    where
+        Self: Sized + 'static,
+    {
+        Arc::new(self)
+    }
     // TODO add search
-    // TODO add id selection
     // TODO split library and input (http, file, s3, ...)
 }
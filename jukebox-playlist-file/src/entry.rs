@@ -0,0 +1,74 @@
+use std::{io, path::Path};
+
+/// A song referenced by a playlist file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Absolute path or URL
+    pub location: String,
+    pub title: Option<String>,
+    /// Duration in seconds, negative when unknown
+    pub duration: Option<i64>,
+}
+
+/// Read a playlist file, the format is detected from its extension or content.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<Entry>> {
+    let path = path.as_ref();
+    let content = std::fs::read(path)?;
+    // M3U files are usually Latin-1, M3U8 and PLS files UTF-8
+    let content = match String::from_utf8(content) {
+        Ok(content) => content,
+        Err(e) => e.into_bytes().iter().map(|&c| c as char).collect(),
+    };
+    let content = content.trim_start_matches('\u{feff}');
+    let base = path.parent().unwrap_or(Path::new(""));
+
+    let is_pls = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pls"))
+        || content.trim_start().starts_with("[playlist]");
+    match is_pls {
+        true => Ok(crate::pls::parse(content, base)),
+        false => Ok(crate::m3u::parse(content, base)),
+    }
+}
+
+/// Resolve a playlist location against the directory of the playlist file.
+pub(crate) fn resolve(location: &str, base: &Path) -> String {
+    if let Some(path) = location.strip_prefix("file://") {
+        return percent_decode(path);
+    }
+    if location.contains("://") {
+        return location.to_string();
+    }
+
+    let location = location.replace('\\', "/");
+    let path = Path::new(&location);
+    match path.is_absolute() {
+        true => location,
+        false => base.join(path).to_string_lossy().to_string(),
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match (bytes[idx], bytes.get(idx + 1..idx + 3)) {
+            (b'%', Some(hex)) => match std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                Some(c) => {
+                    decoded.push(c);
+                    idx += 3;
+                    continue;
+                }
+                None => decoded.push(b'%'),
+            },
+            (c, _) => decoded.push(c),
+        }
+        idx += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
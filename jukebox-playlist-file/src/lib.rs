@@ -0,0 +1,15 @@
+//! # Playlist files
+//!
+//! Play M3U/M3U8 and PLS playlist files, and write songs back as M3U.
+//!
+//! - `Entry`: A location read from a playlist file, with its optional title and duration.
+//! - `Playlist`: A [`Playlist`](jukebox_playlist::Playlist) playing the entries found in the library.
+
+mod entry;
+mod m3u;
+mod playlist;
+mod pls;
+
+pub use entry::{Entry, read};
+pub use m3u::write as write_m3u;
+pub use playlist::PlaylistFile as Playlist;
@@ -0,0 +1,112 @@
+use std::path::Path;
+
+use jukebox_library::Song;
+
+use crate::entry::{Entry, resolve};
+
+pub(crate) fn parse(content: &str, base: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut info: Option<(Option<i64>, Option<String>)> = None;
+
+    for line in content.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // #EXTINF:<duration> [attributes],<title>
+            let (head, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            let duration = head.split_whitespace().next().and_then(|d| d.parse().ok());
+            let title = Some(title.trim().to_string()).filter(|t| !t.is_empty());
+            info = Some((duration, title));
+        } else if !line.starts_with('#') {
+            let (duration, title) = info.take().unwrap_or_default();
+            entries.push(Entry {
+                location: resolve(line, base),
+                title,
+                duration,
+            });
+        }
+    }
+    entries
+}
+
+/// Write songs as an extended M3U playlist, `location` gives the URL of each
+/// song.
+pub fn write<'a>(
+    songs: impl IntoIterator<Item = &'a Song>,
+    location: impl Fn(&Song) -> String,
+) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    for song in songs {
+        let title = song.title_line();
+        content.push_str(&format!("#EXTINF:-1,{title}\n{}\n", location(song)));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use jukebox_library::{Metadata, Stats};
+
+    use super::*;
+
+    #[test]
+    fn parse_extended() {
+        let content = "#EXTM3U\n#EXTINF:123 tvg-id=\"x\",Artist - Title\nsong.mp3\n\n#EXTINF:-1,\n/abs/other.mp3\nhttp://host/stream\n";
+        let entries = parse(content, Path::new("/music"));
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    location: "/music/song.mp3".to_string(),
+                    title: Some("Artist - Title".to_string()),
+                    duration: Some(123),
+                },
+                Entry {
+                    location: "/abs/other.mp3".to_string(),
+                    title: None,
+                    duration: Some(-1),
+                },
+                Entry {
+                    location: "http://host/stream".to_string(),
+                    title: None,
+                    duration: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_file_url() {
+        let entries = parse("file:///music/a%20b.mp3\n", Path::new("/"));
+        assert_eq!(entries[0].location, "/music/a b.mp3");
+    }
+
+    #[test]
+    fn write_titles_on_one_line() {
+        let songs = [
+            Song {
+                id: 0,
+                path: "/music/first.mp3".to_string(),
+                metadata: Metadata {
+                    artist: Some("Artist".to_string()),
+                    title: Some("Two\r\nlines".to_string()),
+                    ..Default::default()
+                },
+                stats: Stats::default(),
+            },
+            Song {
+                id: 1,
+                path: "/music/second.mp3".to_string(),
+                metadata: Metadata::default(),
+                stats: Stats::default(),
+            },
+        ];
+        let content = write(&songs, |song| format!("http://host/{}", song.id));
+        assert_eq!(
+            content,
+            "#EXTM3U\n#EXTINF:-1,Artist - Two  lines\nhttp://host/0\n#EXTINF:-1,second\nhttp://host/1\n"
+        );
+
+        let entries = parse(&content, Path::new("/"));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].title.as_deref(), Some("second"));
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use jukebox_library::{Library, LibraryId};
+use jukebox_playlist::{Empty, Playlist, Prefetch, Stream};
+use rand::seq::SliceRandom;
+use tracing::warn;
+
+use crate::Entry;
+
+/// Play the songs of a playlist file found in the library, in order or shuffled.
+#[derive(Debug, Clone)]
+pub struct PlaylistFile<T: Library> {
+    /// Entries with their canonical path
+    entries: Arc<Vec<(Entry, PathBuf)>>,
+    shuffle: bool,
+
+    /// Songs of the current round, `None` until resolved against the library
+    songs: Option<Vec<LibraryId>>,
+    /// Index in `songs` of the current song
+    position: Option<usize>,
+    prefetch: Prefetch<LibraryId>,
+    library: T,
+}
+
+impl<T> PlaylistFile<T>
+where
+    T: Library,
+{
+    /// Play `entries`, their paths are made canonical once here. Blocks on the
+    /// file system, like `open`.
+    pub fn new(entries: Vec<Entry>, library: T) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|entry| {
+                let path = std::fs::canonicalize(&entry.location)
+                    .unwrap_or_else(|_| PathBuf::from(&entry.location));
+                (entry, path)
+            })
+            .collect();
+        Self {
+            entries: Arc::new(entries),
+            shuffle: false,
+            songs: None,
+            position: None,
+            prefetch: Default::default(),
+            library,
+        }
+    }
+
+    /// Read a M3U or PLS playlist file, blocking on the file system.
+    pub fn open(path: impl AsRef<Path>, library: T) -> io::Result<Self> {
+        Ok(Self::new(crate::read(path)?, library))
+    }
+
+    /// Shuffle the songs again at each round.
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Find the entries in the library, the ones not found are skipped.
+    async fn resolve(&self) -> Vec<LibraryId> {
+        let mut paths = HashMap::new();
+        for id in self.library.ids().await {
+            if let Some(song) = self.library.song(id).await {
+                let path = tokio::fs::canonicalize(&song.path)
+                    .await
+                    .unwrap_or_else(|_| PathBuf::from(&song.path));
+                paths.insert(path, id);
+            }
+        }
+
+        self.entries
+            .iter()
+            .filter_map(|(entry, path)| {
+                let id = paths.get(path).copied();
+                if id.is_none() {
+                    warn!("playlist: {} not found in library", entry.location);
+                }
+                id
+            })
+            .collect()
+    }
+
+    async fn new_round(&mut self) {
+        let mut songs = self.resolve().await;
+        if self.shuffle {
+            songs.shuffle(&mut rand::rng());
+        }
+        self.songs = Some(songs);
+        self.position = None;
+    }
+
+    /// Index of the song played by the following `next`.
+    async fn upcoming(&mut self) -> Option<usize> {
+        if self.songs.is_none() {
+            self.new_round().await;
+        }
+
+        let len = self.songs.as_ref().map(Vec::len).unwrap_or_default();
+        match self.position {
+            None => {}
+            Some(position) if position + 1 < len => return Some(position + 1),
+            Some(_) => self.new_round().await,
+        }
+        self.songs
+            .as_ref()
+            .filter(|songs| !songs.is_empty())
+            .map(|_| 0)
+    }
+
+    fn song(&self, index: usize) -> Option<LibraryId> {
+        self.songs.as_ref()?.get(index).copied()
+    }
+}
+
+impl<T> Playlist for PlaylistFile<T>
+where
+    T: Library,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        let len = self.songs.as_ref().map(Vec::len).unwrap_or(1);
+        // Skip the songs removed from the library, at most one round
+        for _ in 0..len.max(1) {
+            let Some(index) = self.upcoming().await else {
+                break;
+            };
+            self.position = Some(index);
+            let Some(id) = self.song(index) else {
+                break;
+            };
+            if let Some(stream) = self.prefetch.take_or_get(id, &self.library).await {
+                return stream;
+            }
+        }
+
+        Box::new(Empty)
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        if let Some(position) = self.position
+            && position > 0
+        {
+            self.position = Some(position - 1);
+        }
+        self.rewind().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.current()
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
+        self.next().await
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.song(self.position?)
+    }
+
+    async fn prefetch(&mut self) {
+        // Don't start a new round early, it would change the current song
+        let next = match (&self.songs, self.position) {
+            (Some(songs), Some(position)) => songs.get(position + 1).copied(),
+            _ => None,
+        };
+        if let Some(id) = next
+            && self.prefetch.id() != Some(&id)
+            && let Some(stream) = self.library.get(id).await
+        {
+            self.prefetch.set(id, stream);
+        }
+    }
+}
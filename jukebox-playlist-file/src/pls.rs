@@ -0,0 +1,72 @@
+use std::{collections::BTreeMap, path::Path};
+
+use crate::entry::{Entry, resolve};
+
+#[derive(Default)]
+struct PlsEntry {
+    file: Option<String>,
+    title: Option<String>,
+    length: Option<i64>,
+}
+
+pub(crate) fn parse(content: &str, base: &Path) -> Vec<Entry> {
+    let mut entries: BTreeMap<u32, PlsEntry> = BTreeMap::new();
+
+    for line in content.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim();
+        let (field, index) =
+            key.split_at(key.find(|c: char| c.is_ascii_digit()).unwrap_or(key.len()));
+        let Ok(index) = index.parse() else {
+            continue;
+        };
+
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.file = Some(resolve(value, base)),
+            "title" => entry.title = Some(value.to_string()).filter(|t| !t.is_empty()),
+            "length" => entry.length = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    entries
+        .into_values()
+        .filter_map(|entry| {
+            Some(Entry {
+                location: entry.file?,
+                title: entry.title,
+                duration: entry.length,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_by_index() {
+        let content = "[playlist]\nNumberOfEntries=3\nFile2=b.mp3\nTitle2=Second\nLength2=-1\nFile1=/abs/a.mp3\nTitle1=First\nLength1=200\nTitle3=No file\nVersion=2\n";
+        let entries = parse(content, Path::new("/music"));
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    location: "/abs/a.mp3".to_string(),
+                    title: Some("First".to_string()),
+                    duration: Some(200),
+                },
+                Entry {
+                    location: "/music/b.mp3".to_string(),
+                    title: Some("Second".to_string()),
+                    duration: Some(-1),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use jukebox_library::{Library, LibraryId};
+use jukebox_playlist::{Playlist, Prefetch, Stream};
+use rand::seq::IndexedRandom;
+use tokio::sync::broadcast;
+
+/// When a jingle is inserted, the first reached condition wins.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rules {
+    /// Insert a jingle every this number of songs
+    pub tracks: Option<usize>,
+    /// Insert a jingle at the first song boundary once this time elapsed
+    pub interval: Option<Duration>,
+}
+
+/// Rule which triggered an insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Tracks,
+    Interval,
+}
+
+/// A jingle inserted between two songs.
+#[derive(Debug, Clone)]
+pub struct Insertion {
+    /// Identifier in the jingle library
+    pub id: LibraryId,
+    pub path: Option<String>,
+    pub reason: Reason,
+    pub time: SystemTime,
+}
+
+/// Interleave jingles from a separate library with the songs of the inner playlist.
+#[derive(Debug, Clone)]
+pub struct PlaylistJingle<P, L>
+where
+    P: Playlist,
+    L: Library,
+{
+    rules: Rules,
+    /// Songs played since the last jingle
+    tracks: usize,
+    last: Instant,
+    /// Jingle currently playing
+    jingle: Option<LibraryId>,
+    prefetch: Prefetch<LibraryId>,
+    events: broadcast::Sender<Insertion>,
+    inner: P,
+    jingles: L,
+}
+
+impl<P, L> PlaylistJingle<P, L>
+where
+    P: Playlist,
+    L: Library,
+{
+    const EVENTS_SIZE: usize = 16;
+
+    pub fn new(inner: P, jingles: L, rules: Rules) -> Self {
+        Self {
+            rules,
+            tracks: 0,
+            last: Instant::now(),
+            jingle: None,
+            prefetch: Default::default(),
+            events: broadcast::channel(Self::EVENTS_SIZE).0,
+            inner,
+            jingles,
+        }
+    }
+
+    /// Receive the insertions of this playlist and of its clones.
+    pub fn subscribe(&self) -> broadcast::Receiver<Insertion> {
+        self.events.subscribe()
+    }
+
+    /// Rule requiring a jingle before the next song, never twice in a row.
+    fn due(&self) -> Option<Reason> {
+        if self.tracks == 0 {
+            return None;
+        }
+        match self.rules {
+            Rules {
+                tracks: Some(tracks),
+                ..
+            } if self.tracks >= tracks => Some(Reason::Tracks),
+            Rules {
+                interval: Some(interval),
+                ..
+            } if self.last.elapsed() >= interval => Some(Reason::Interval),
+            _ => None,
+        }
+    }
+
+    /// Pick a jingle, avoiding the previous one when possible.
+    ///
+    /// Only borrows the library, the inner playlist doesn't have to be `Sync`.
+    async fn pick(
+        jingles: &L,
+        previous: Option<LibraryId>,
+    ) -> Option<(LibraryId, Box<dyn Stream>)> {
+        let ids: Vec<LibraryId> = jingles
+            .ids()
+            .await
+            .into_iter()
+            .filter(|&id| Some(id) != previous)
+            .collect();
+        let id = match ids.choose(&mut rand::rng()) {
+            Some(&id) => id,
+            None => previous?,
+        };
+        Some((id, jingles.get(id).await?))
+    }
+}
+
+impl<P, L> Playlist for PlaylistJingle<P, L>
+where
+    P: Playlist,
+    L: Library,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        if let Some(reason) = self.due() {
+            let jingle = match self.prefetch.take() {
+                Some(jingle) => Some(jingle),
+                None => Self::pick(&self.jingles, self.jingle).await,
+            };
+            if let Some((id, stream)) = jingle {
+                self.jingle = Some(id);
+                self.tracks = 0;
+                self.last = Instant::now();
+                let _ = self.events.send(Insertion {
+                    id,
+                    path: self.jingles.song(id).await.map(|song| song.path),
+                    reason,
+                    time: SystemTime::now(),
+                });
+                return stream;
+            }
+        }
+
+        self.jingle = None;
+        self.tracks += 1;
+        self.inner.next().await
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        self.jingle = None;
+        self.inner.prev().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.jingle
+            && let Some(stream) = self.jingles.get(id).await
+        {
+            return stream;
+        }
+
+        self.inner.rewind().await
+    }
+
+    fn skip(&mut self) {
+        // Skipping a jingle only ends it, the following song still plays
+        if self.jingle.take().is_none() {
+            self.inner.skip()
+        }
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        match self.jingle {
+            Some(_) => None,
+            None => self.inner.current(),
+        }
+    }
+
+    async fn prefetch(&mut self) {
+        if self.due().is_none() {
+            return self.inner.prefetch().await;
+        }
+        if !self.prefetch.is_some()
+            && let Some((id, stream)) = Self::pick(&self.jingles, self.jingle).await
+        {
+            self.prefetch.set(id, stream);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jukebox_library::Song;
+    use jukebox_playlist::Empty;
+
+    use super::*;
+
+    /// Library of `0..n` empty songs.
+    #[derive(Clone)]
+    struct Songs(usize);
+
+    impl Library for Songs {
+        async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+            None
+        }
+
+        async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
+            (id < self.0).then(|| Box::new(Empty) as Box<dyn Stream>)
+        }
+
+        async fn ids(&self) -> Vec<LibraryId> {
+            (0..self.0).collect()
+        }
+
+        async fn song(&self, id: LibraryId) -> Option<Song> {
+            (id < self.0).then(|| Song {
+                id,
+                path: format!("{id}.mp3"),
+                metadata: Default::default(),
+                stats: Default::default(),
+            })
+        }
+    }
+
+    /// Plays 0, 1, 2... and counts the skips.
+    #[derive(Debug, Clone, Default)]
+    struct Counter {
+        position: Option<LibraryId>,
+        skips: usize,
+    }
+
+    impl Playlist for Counter {
+        async fn next(&mut self) -> Box<dyn Stream> {
+            self.position = Some(self.position.map_or(0, |position| position + 1));
+            Box::new(Empty)
+        }
+
+        async fn prev(&mut self) -> Box<dyn Stream> {
+            self.position = self.position.map(|position| position.saturating_sub(1));
+            Box::new(Empty)
+        }
+
+        async fn rewind(&mut self) -> Box<dyn Stream> {
+            Box::new(Empty)
+        }
+
+        fn skip(&mut self) {
+            self.skips += 1;
+        }
+
+        fn current(&self) -> Option<LibraryId> {
+            self.position
+        }
+    }
+
+    fn with_rules(rules: Rules) -> PlaylistJingle<Counter, Songs> {
+        PlaylistJingle::new(Counter::default(), Songs(2), rules)
+    }
+
+    /// Songs played by `count` calls to `next`, `None` for a jingle.
+    async fn play(
+        playlist: &mut PlaylistJingle<Counter, Songs>,
+        count: usize,
+    ) -> Vec<Option<LibraryId>> {
+        let mut played = Vec::new();
+        for _ in 0..count {
+            playlist.next().await;
+            played.push(playlist.current());
+        }
+        played
+    }
+
+    #[tokio::test]
+    async fn every_tracks() {
+        let mut playlist = with_rules(Rules {
+            tracks: Some(2),
+            interval: None,
+        });
+        let mut events = playlist.subscribe();
+
+        assert_eq!(
+            play(&mut playlist, 7).await,
+            [Some(0), Some(1), None, Some(2), Some(3), None, Some(4)]
+        );
+        let insertion = events.try_recv().unwrap();
+        assert_eq!(insertion.reason, Reason::Tracks);
+        assert_eq!(insertion.path, Some(format!("{}.mp3", insertion.id)));
+    }
+
+    #[tokio::test]
+    async fn interval() {
+        let mut playlist = with_rules(Rules {
+            tracks: None,
+            interval: Some(Duration::ZERO),
+        });
+        assert_eq!(playlist.due(), None);
+        // Never two jingles in a row
+        assert_eq!(play(&mut playlist, 4).await, [Some(0), None, Some(1), None]);
+
+        let mut hourly = with_rules(Rules {
+            tracks: None,
+            interval: Some(Duration::from_secs(3600)),
+        });
+        assert_eq!(play(&mut hourly, 3).await, [Some(0), Some(1), Some(2)]);
+        assert_eq!(hourly.due(), None);
+    }
+
+    #[tokio::test]
+    async fn prefetch_jingle() {
+        let mut playlist = with_rules(Rules {
+            tracks: Some(1),
+            interval: None,
+        });
+        playlist.next().await;
+        assert_eq!(playlist.due(), Some(Reason::Tracks));
+
+        playlist.prefetch().await;
+        let prefetched = *playlist.prefetch.id().unwrap();
+        playlist.next().await;
+        assert_eq!(playlist.jingle, Some(prefetched));
+        assert_eq!(playlist.current(), None);
+        assert!(!playlist.prefetch.is_some());
+    }
+
+    #[tokio::test]
+    async fn skip_jingle() {
+        let mut playlist = with_rules(Rules {
+            tracks: Some(1),
+            interval: None,
+        });
+        playlist.next().await;
+        playlist.skip();
+        assert_eq!(playlist.inner.skips, 1);
+
+        playlist.next().await;
+        assert_eq!(playlist.current(), None);
+        playlist.skip();
+        assert_eq!(playlist.inner.skips, 1);
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(1));
+    }
+}
@@ -0,0 +1,12 @@
+//! # Jingles
+//!
+//! Insert station IDs, jingles or ads from a separate library between the songs
+//! of a playlist.
+//!
+//! Jingles are not reported as the current song, so they are not part of the
+//! channel history and can't be voted on. Each insertion is published to the
+//! subscribers of the playlist.
+
+mod jingle;
+
+pub use jingle::{Insertion, PlaylistJingle as Playlist, Reason, Rules};
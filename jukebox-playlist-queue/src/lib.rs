@@ -0,0 +1,7 @@
+mod playlist;
+mod queue;
+mod ranking;
+
+pub use playlist::PlaylistQueue as Playlist;
+pub use queue::{Queue, QueueError, QueueId, QueueItem};
+pub use ranking::Ranking;
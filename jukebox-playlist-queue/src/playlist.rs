@@ -0,0 +1,208 @@
+use jukebox_library::{Library, LibraryId};
+use jukebox_playlist::{Playlist, Prefetch, Stream};
+
+use crate::{Queue, QueueId, Ranking};
+
+/// Play the songs requested by users first, in the order of `R`, then fall
+/// back to the inner playlist.
+#[derive(Debug, Clone)]
+pub struct PlaylistQueue<P, L, R = Queue>
+where
+    P: Playlist,
+    L: Library,
+    R: Ranking,
+{
+    queue: R,
+    /// Current song when it comes from the queue
+    current: Option<LibraryId>,
+    prefetch: Prefetch<QueueId>,
+    inner: P,
+    library: L,
+}
+
+impl<P, L, R> PlaylistQueue<P, L, R>
+where
+    P: Playlist,
+    L: Library,
+    R: Ranking,
+{
+    pub fn new(inner: P, library: L, queue: R) -> Self {
+        Self {
+            queue,
+            current: None,
+            prefetch: Default::default(),
+            inner,
+            library,
+        }
+    }
+
+    pub fn queue(&self) -> &R {
+        &self.queue
+    }
+}
+
+impl<P, L, R> Playlist for PlaylistQueue<P, L, R>
+where
+    P: Playlist,
+    L: Library,
+    R: Ranking,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        self.queue.started();
+        while let Some(item) = self.queue.pop() {
+            let stream = match self.prefetch.id() {
+                Some(id) if *id == item.id => self.prefetch.take().map(|(_, stream)| stream),
+                _ => self.library.get(item.song).await,
+            };
+            if let Some(stream) = stream {
+                self.current = Some(item.song);
+                return stream;
+            }
+        }
+
+        self.current = None;
+        self.inner.next().await
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        self.queue.started();
+        self.current = None;
+        self.inner.prev().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.current
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
+        self.inner.rewind().await
+    }
+
+    fn skip(&mut self) {
+        self.inner.skip()
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.current.or_else(|| self.inner.current())
+    }
+
+    async fn prefetch(&mut self) {
+        match self.queue.front() {
+            Some(item) if self.prefetch.id() == Some(&item.id) => {}
+            Some(item) => {
+                if let Some(stream) = self.library.get(item.song).await {
+                    self.prefetch.set(item.id, stream);
+                }
+            }
+            None => self.inner.prefetch().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jukebox_library::Song;
+    use jukebox_playlist::Empty;
+
+    use super::*;
+
+    /// Library of `0..n` empty songs.
+    #[derive(Clone)]
+    struct Songs(usize);
+
+    impl Library for Songs {
+        async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+            None
+        }
+
+        async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
+            (id < self.0).then(|| Box::new(Empty) as Box<dyn Stream>)
+        }
+
+        async fn ids(&self) -> Vec<LibraryId> {
+            (0..self.0).collect()
+        }
+
+        async fn song(&self, id: LibraryId) -> Option<Song> {
+            (id < self.0).then(|| Song {
+                id,
+                path: format!("{id}.mp3"),
+                metadata: Default::default(),
+                stats: Default::default(),
+            })
+        }
+    }
+
+    /// Plays 100, 101, 102...
+    #[derive(Debug, Clone, Default)]
+    struct Counter(Option<LibraryId>);
+
+    impl Playlist for Counter {
+        async fn next(&mut self) -> Box<dyn Stream> {
+            self.0 = Some(self.0.map_or(100, |position| position + 1));
+            Box::new(Empty)
+        }
+
+        async fn prev(&mut self) -> Box<dyn Stream> {
+            self.0 = self.0.map(|position| position.saturating_sub(1));
+            Box::new(Empty)
+        }
+
+        async fn rewind(&mut self) -> Box<dyn Stream> {
+            Box::new(Empty)
+        }
+
+        fn current(&self) -> Option<LibraryId> {
+            self.0
+        }
+    }
+
+    async fn play(
+        playlist: &mut PlaylistQueue<Counter, Songs>,
+        count: usize,
+    ) -> Vec<Option<LibraryId>> {
+        let mut played = Vec::new();
+        for _ in 0..count {
+            playlist.prefetch().await;
+            playlist.next().await;
+            played.push(playlist.current());
+        }
+        played
+    }
+
+    #[tokio::test]
+    async fn queue_first() {
+        let mut playlist = PlaylistQueue::new(Counter::default(), Songs(5), Queue::default());
+        playlist.queue().push(3, "alice").unwrap();
+        playlist.queue().push(1, "bob").unwrap();
+
+        assert_eq!(
+            play(&mut playlist, 4).await,
+            [Some(3), Some(1), Some(100), Some(101)]
+        );
+        assert!(playlist.queue().list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn missing_song() {
+        let mut playlist = PlaylistQueue::new(Counter::default(), Songs(5), Queue::default());
+        playlist.queue().push(10, "alice").unwrap();
+        playlist.queue().push(2, "alice").unwrap();
+
+        assert_eq!(play(&mut playlist, 2).await, [Some(2), Some(100)]);
+    }
+
+    #[tokio::test]
+    async fn removed_after_prefetch() {
+        let mut playlist = PlaylistQueue::new(Counter::default(), Songs(5), Queue::default());
+        let id = playlist.queue().push(3, "alice").unwrap();
+        playlist.queue().push(4, "alice").unwrap();
+
+        playlist.prefetch().await;
+        playlist.queue().remove(id);
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(4));
+    }
+}
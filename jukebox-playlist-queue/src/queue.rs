@@ -0,0 +1,160 @@
+use std::{
+    collections::VecDeque,
+    error::Error as StdError,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use jukebox_library::LibraryId;
+
+pub type QueueId = u64;
+
+/// A song requested by a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueItem {
+    pub id: QueueId,
+    pub song: LibraryId,
+    pub user: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueueError {
+    /// The user already has the maximum number of queued songs
+    Limit,
+}
+
+impl StdError for QueueError {}
+impl Display for QueueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueError::Limit => write!(f, "too many queued songs"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct QueueInner {
+    items: VecDeque<QueueItem>,
+    next_id: QueueId,
+}
+
+/// Songs requested by users, shared between a channel playlist and the API.
+#[derive(Debug, Clone)]
+pub struct Queue {
+    inner: Arc<Mutex<QueueInner>>,
+    /// Maximum number of queued songs per user
+    limit: usize,
+}
+
+impl Default for Queue {
+    fn default() -> Self {
+        Self::new(Self::LIMIT)
+    }
+}
+
+impl Queue {
+    const LIMIT: usize = 3;
+
+    pub fn new(limit: usize) -> Self {
+        Self {
+            inner: Default::default(),
+            limit,
+        }
+    }
+
+    /// Append a song at the end of the queue.
+    pub fn push(&self, song: LibraryId, user: impl Into<String>) -> Result<QueueId, QueueError> {
+        let user = user.into();
+        let mut inner = self.inner.lock().unwrap();
+        if inner.items.iter().filter(|item| item.user == user).count() >= self.limit {
+            return Err(QueueError::Limit);
+        }
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.items.push_back(QueueItem { id, song, user });
+        Ok(id)
+    }
+
+    pub fn list(&self) -> Vec<QueueItem> {
+        self.inner.lock().unwrap().items.iter().cloned().collect()
+    }
+
+    pub fn remove(&self, id: QueueId) -> Option<QueueItem> {
+        let mut inner = self.inner.lock().unwrap();
+        let index = inner.items.iter().position(|item| item.id == id)?;
+        inner.items.remove(index)
+    }
+
+    /// Move a queued song to `position`, clamped to the end of the queue.
+    pub fn reorder(&self, id: QueueId, position: usize) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        let Some(item) = inner
+            .items
+            .iter()
+            .position(|item| item.id == id)
+            .and_then(|index| inner.items.remove(index))
+        else {
+            return false;
+        };
+        let position = position.min(inner.items.len());
+        inner.items.insert(position, item);
+        true
+    }
+
+    pub(crate) fn front(&self) -> Option<QueueItem> {
+        self.inner.lock().unwrap().items.front().cloned()
+    }
+
+    pub(crate) fn pop(&self) -> Option<QueueItem> {
+        self.inner.lock().unwrap().items.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn songs(queue: &Queue) -> Vec<LibraryId> {
+        queue.list().into_iter().map(|item| item.song).collect()
+    }
+
+    #[test]
+    fn limit() {
+        let queue = Queue::new(2);
+        assert_eq!(queue.push(0, "alice"), Ok(0));
+        assert_eq!(queue.push(1, "alice"), Ok(1));
+        assert_eq!(queue.push(2, "alice"), Err(QueueError::Limit));
+        assert_eq!(queue.push(2, "bob"), Ok(2));
+        assert_eq!(songs(&queue), [0, 1, 2]);
+
+        // Played songs no longer count
+        queue.pop();
+        assert_eq!(queue.push(3, "alice"), Ok(3));
+    }
+
+    #[test]
+    fn remove() {
+        let queue = Queue::default();
+        let id = queue.push(0, "alice").unwrap();
+        queue.push(1, "alice").unwrap();
+
+        assert_eq!(queue.remove(id).map(|item| item.song), Some(0));
+        assert_eq!(queue.remove(id), None);
+        assert_eq!(songs(&queue), [1]);
+    }
+
+    #[test]
+    fn reorder() {
+        let queue = Queue::default();
+        let ids: Vec<_> = (0..3)
+            .map(|song| queue.push(song, "alice").unwrap())
+            .collect();
+
+        assert!(queue.reorder(ids[2], 0));
+        assert_eq!(songs(&queue), [2, 0, 1]);
+        assert!(queue.reorder(ids[2], 10));
+        assert_eq!(songs(&queue), [0, 1, 2]);
+        assert!(!queue.reorder(10, 0));
+    }
+}
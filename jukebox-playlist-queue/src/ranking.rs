@@ -0,0 +1,23 @@
+use crate::{Queue, QueueItem};
+
+/// Order in which a [`Playlist`](crate::Playlist) plays the requested songs.
+///
+/// [`Queue`] plays them in the order they were requested.
+pub trait Ranking: Clone + Send + Sync {
+    /// Song to play next, left in the queue.
+    fn front(&self) -> Option<QueueItem>;
+    /// Remove the song to play next from the queue.
+    fn pop(&self) -> Option<QueueItem>;
+    /// Another song started, by request or not.
+    fn started(&self) {}
+}
+
+impl Ranking for Queue {
+    fn front(&self) -> Option<QueueItem> {
+        Queue::front(self)
+    }
+
+    fn pop(&self) -> Option<QueueItem> {
+        Queue::pop(self)
+    }
+}
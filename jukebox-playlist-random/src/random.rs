@@ -1,9 +1,17 @@
+use std::collections::VecDeque;
+
 use jukebox_library::{Library, LibraryId};
-use jukebox_playlist::{Playlist, Stream};
+use jukebox_playlist::{Empty, Playlist, Prefetch, Stream};
 
 #[derive(Debug, Clone)]
 pub struct PlaylistRandom<T: Library> {
     current: Option<LibraryId>,
+    /// Played songs, the most recent at the back
+    history: VecDeque<LibraryId>,
+    history_size: usize,
+    /// Songs left by `prev`, the next one at the top
+    forward: Vec<LibraryId>,
+    prefetch: Prefetch<LibraryId>,
     library: T,
 }
 
@@ -11,10 +19,32 @@ impl<T> PlaylistRandom<T>
 where
     T: Library,
 {
+    const HISTORY_SIZE: usize = 100;
+
     pub fn new(library: T) -> Self {
         Self {
             library,
             current: None,
+            history: Default::default(),
+            history_size: Self::HISTORY_SIZE,
+            forward: Default::default(),
+            prefetch: Default::default(),
+        }
+    }
+
+    /// Set the number of songs remembered for `prev`.
+    pub fn with_history(mut self, size: usize) -> Self {
+        self.history_size = size;
+        self.history.truncate(size);
+        self
+    }
+
+    fn play(&mut self, id: LibraryId) {
+        if let Some(current) = self.current.replace(id) {
+            self.history.push_back(current);
+            while self.history.len() > self.history_size {
+                self.history.pop_front();
+            }
         }
     }
 }
@@ -24,22 +54,154 @@ where
     T: Library,
 {
     async fn next(&mut self) -> Box<dyn Stream> {
-        let (song_id, stream) = self.library.random().await;
-        self.current = Some(song_id);
-        stream
-    }
+        while let Some(id) = self.forward.pop() {
+            if let Some(stream) = self.prefetch.take_or_get(id, &self.library).await {
+                self.play(id);
+                return stream;
+            }
+        }
 
-    async fn prev(&mut self) -> Box<dyn Stream> {
-        self.next().await
+        let prefetch = match self.prefetch.take() {
+            Some(prefetch) => Some(prefetch),
+            None => self.library.random().await,
+        };
+        match prefetch {
+            Some((song_id, stream)) => {
+                self.play(song_id);
+                stream
+            }
+            // Empty library
+            None => Box::new(Empty),
+        }
     }
 
-    async fn rewind(&mut self) -> Box<dyn Stream> {
-        if let Some(id) = self.current {
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        while let Some(id) = self.history.pop_back() {
             if let Some(stream) = self.library.get(id).await {
+                if let Some(current) = self.current.replace(id) {
+                    self.forward.push(current);
+                }
                 return stream;
             }
         }
 
+        self.rewind().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.current
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
         self.next().await
     }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.current
+    }
+
+    async fn prefetch(&mut self) {
+        match self.forward.last().copied() {
+            Some(id) if self.prefetch.id() != Some(&id) => {
+                if let Some(stream) = self.library.get(id).await {
+                    self.prefetch.set(id, stream);
+                }
+            }
+            Some(_) => {}
+            None if self.prefetch.is_some() => {}
+            None => {
+                if let Some((song_id, stream)) = self.library.random().await {
+                    self.prefetch.set(song_id, stream);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use jukebox_library::Song;
+
+    use super::*;
+
+    /// Library whose random songs are 0, 1, 2...
+    #[derive(Clone, Default)]
+    struct Songs(Arc<AtomicUsize>);
+
+    impl Library for Songs {
+        async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+            let id = self.0.fetch_add(1, Ordering::Relaxed);
+            Some((id, Box::new(Empty)))
+        }
+
+        async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
+            (id < self.0.load(Ordering::Relaxed)).then(|| Box::new(Empty) as Box<dyn Stream>)
+        }
+
+        async fn ids(&self) -> Vec<LibraryId> {
+            (0..self.0.load(Ordering::Relaxed)).collect()
+        }
+
+        async fn song(&self, _id: LibraryId) -> Option<Song> {
+            None
+        }
+    }
+
+    #[tokio::test]
+    async fn prev_then_next() {
+        let mut playlist = PlaylistRandom::new(Songs::default());
+        for _ in 0..3 {
+            playlist.next().await;
+        }
+        assert_eq!(playlist.current(), Some(2));
+
+        playlist.prev().await;
+        playlist.prev().await;
+        assert_eq!(playlist.current(), Some(0));
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(1));
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(2));
+        // Back to random songs
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn history_size() {
+        let mut playlist = PlaylistRandom::new(Songs::default()).with_history(1);
+        for _ in 0..3 {
+            playlist.next().await;
+        }
+
+        playlist.prev().await;
+        assert_eq!(playlist.current(), Some(1));
+        // Nothing older is remembered, the song starts again
+        playlist.prev().await;
+        assert_eq!(playlist.current(), Some(1));
+    }
+
+    #[tokio::test]
+    async fn prefetch_forward() {
+        let mut playlist = PlaylistRandom::new(Songs::default());
+        playlist.prefetch().await;
+        assert_eq!(playlist.prefetch.id(), Some(&0));
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(0));
+        playlist.next().await;
+
+        playlist.prev().await;
+        playlist.prefetch().await;
+        assert_eq!(playlist.prefetch.id(), Some(&1));
+        playlist.next().await;
+        assert_eq!(playlist.current(), Some(1));
+        assert!(!playlist.prefetch.is_some());
+    }
 }
@@ -0,0 +1,17 @@
+//! # Scheduled programming
+//!
+//! Switch between playlists depending on the local time, the switch happens at
+//! the next song boundary.
+//!
+//! - `Rule`: When a program is active, like `mon-fri 09:00-12:00` or `fri 17:00-24:00`.
+//!   Days are `*`, a day, a range of days or a comma separated list of both.
+//! - `Schedule`: The programs and the active one, shared with the API.
+//! - `Playlist`: A [`Playlist`](jukebox_playlist::Playlist) delegating to the active program.
+
+mod playlist;
+mod rule;
+mod schedule;
+
+pub use playlist::PlaylistSchedule as Playlist;
+pub use rule::{Rule, RuleError};
+pub use schedule::{Program, Schedule};
@@ -0,0 +1,112 @@
+use chrono::Local;
+use jukebox_playlist::{LibraryId, Playlist, Stream};
+use tracing::info;
+
+use crate::{Program, Schedule};
+
+/// Delegate to the first program whose rule matches the local time, or to the
+/// default playlist.
+#[derive(Debug, Clone)]
+pub struct PlaylistSchedule<D: Playlist, P: Playlist> {
+    programs: Vec<Program<P>>,
+    default: D,
+    /// Index in `programs` of the active program, `None` for the default playlist
+    active: Option<usize>,
+    schedule: Schedule,
+}
+
+impl<D, P> PlaylistSchedule<D, P>
+where
+    D: Playlist,
+    P: Playlist,
+{
+    const DEFAULT_NAME: &str = "default";
+
+    pub fn new(default: D, programs: Vec<Program<P>>, schedule: Schedule) -> Self {
+        schedule.set_programs(
+            programs
+                .iter()
+                .map(|program| (program.name.clone(), program.rule.to_string()))
+                .collect(),
+        );
+        schedule.set_active(Self::DEFAULT_NAME);
+        Self {
+            programs,
+            default,
+            active: None,
+            schedule,
+        }
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    fn scheduled(&self) -> Option<usize> {
+        let now = Local::now().naive_local();
+        self.programs
+            .iter()
+            .position(|program| program.rule.matches(now))
+    }
+
+    fn name(&self) -> &str {
+        match self.active {
+            Some(index) => &self.programs[index].name,
+            None => Self::DEFAULT_NAME,
+        }
+    }
+}
+
+impl<D, P> Playlist for PlaylistSchedule<D, P>
+where
+    D: Playlist,
+    P: Playlist,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        let scheduled = self.scheduled();
+        if scheduled != self.active {
+            self.active = scheduled;
+            info!("schedule: switch to program {}", self.name());
+            self.schedule.set_active(self.name());
+        }
+        match self.active {
+            Some(index) => self.programs[index].playlist.next().await,
+            None => self.default.next().await,
+        }
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        match self.active {
+            Some(index) => self.programs[index].playlist.prev().await,
+            None => self.default.prev().await,
+        }
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        match self.active {
+            Some(index) => self.programs[index].playlist.rewind().await,
+            None => self.default.rewind().await,
+        }
+    }
+
+    fn skip(&mut self) {
+        match self.active {
+            Some(index) => self.programs[index].playlist.skip(),
+            None => self.default.skip(),
+        }
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        match self.active {
+            Some(index) => self.programs[index].playlist.current(),
+            None => self.default.current(),
+        }
+    }
+
+    async fn prefetch(&mut self) {
+        match self.scheduled() {
+            Some(index) => self.programs[index].playlist.prefetch().await,
+            None => self.default.prefetch().await,
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use std::{error::Error as StdError, fmt::Display, str::FromStr};
+
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Timelike, Weekday};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleError {
+    InvalidDays(String),
+    InvalidTime(String),
+    Format(String),
+}
+
+impl StdError for RuleError {}
+impl Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::InvalidDays(days) => write!(f, "invalid days '{days}'"),
+            RuleError::InvalidTime(time) => write!(f, "invalid time '{time}'"),
+            RuleError::Format(rule) => {
+                write!(
+                    f,
+                    "invalid rule '{rule}', expected '<days> <HH:MM>-<HH:MM>'"
+                )
+            }
+        }
+    }
+}
+
+/// Days and time range during which a program is active.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    /// Active days, indexed from Monday
+    days: [bool; 7],
+    /// Start of the range in minutes from midnight
+    start: u32,
+    /// End of the range in minutes from midnight, excluded
+    end: u32,
+    text: String,
+}
+
+impl Rule {
+    pub fn matches(&self, now: NaiveDateTime) -> bool {
+        let day = now.weekday().num_days_from_monday() as usize;
+        let minute = now.hour() * 60 + now.minute();
+        if self.start <= self.end {
+            self.days[day] && (self.start..self.end).contains(&minute)
+        } else {
+            // Overnight range, the morning part belongs to the previous day
+            let previous = (day + 6) % 7;
+            (self.days[day] && minute >= self.start) || (self.days[previous] && minute < self.end)
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+fn weekday(day: &str) -> Result<usize, RuleError> {
+    day.parse::<Weekday>()
+        .map(|day| day.num_days_from_monday() as usize)
+        .map_err(|_| RuleError::InvalidDays(day.to_string()))
+}
+
+fn days(value: &str) -> Result<[bool; 7], RuleError> {
+    let mut days = [false; 7];
+    if value == "*" {
+        return Ok([true; 7]);
+    }
+    for part in value.split(',') {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (weekday(start)?, weekday(end)?);
+                let mut day = start;
+                loop {
+                    days[day] = true;
+                    if day == end {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[weekday(part)?] = true,
+        }
+    }
+    Ok(days)
+}
+
+fn minutes(value: &str) -> Result<u32, RuleError> {
+    if value == "24:00" {
+        return Ok(24 * 60);
+    }
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .map(|time| time.hour() * 60 + time.minute())
+        .map_err(|_| RuleError::InvalidTime(value.to_string()))
+}
+
+impl FromStr for Rule {
+    type Err = RuleError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let format = || RuleError::Format(value.to_string());
+        let (day_part, time_part) = value.trim().split_once(' ').ok_or_else(format)?;
+        let (start, end) = time_part.trim().split_once('-').ok_or_else(format)?;
+        Ok(Self {
+            days: days(&day_part.to_lowercase())?,
+            start: minutes(start)?,
+            end: minutes(end)?,
+            text: value.trim().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    /// Time of a day of the week of 2024-01-01, a Monday.
+    fn at(day: Weekday, time: &str) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1 + day.num_days_from_monday())
+            .unwrap()
+            .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap())
+    }
+
+    fn rule(rule: &str) -> Rule {
+        rule.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_days() {
+        assert_eq!(days("*"), Ok([true; 7]));
+        assert_eq!(
+            days("mon-wed"),
+            Ok([true, true, true, false, false, false, false])
+        );
+        assert_eq!(
+            days("sat-mon"),
+            Ok([true, false, false, false, false, true, true])
+        );
+        assert_eq!(
+            days("tue,thu-fri"),
+            Ok([false, true, false, true, true, false, false])
+        );
+        assert_eq!(
+            days("noday"),
+            Err(RuleError::InvalidDays("noday".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_rule() {
+        let rule = rule(" Mon-Fri 09:00-12:30 ");
+        assert_eq!((rule.start, rule.end), (9 * 60, 12 * 60 + 30));
+        assert_eq!(rule.to_string(), "Mon-Fri 09:00-12:30");
+        assert_eq!(
+            "mon 9h-12h".parse::<Rule>(),
+            Err(RuleError::InvalidTime("9h".to_string()))
+        );
+        assert_eq!(
+            "09:00-12:00".parse::<Rule>(),
+            Err(RuleError::Format("09:00-12:00".to_string()))
+        );
+    }
+
+    #[test]
+    fn match_range() {
+        let morning = rule("mon-fri 09:00-12:00");
+        assert!(morning.matches(at(Weekday::Mon, "09:00")));
+        assert!(morning.matches(at(Weekday::Fri, "11:59")));
+        assert!(!morning.matches(at(Weekday::Fri, "12:00")));
+        assert!(!morning.matches(at(Weekday::Sat, "10:00")));
+
+        let evening = rule("fri 17:00-24:00");
+        assert!(evening.matches(at(Weekday::Fri, "23:59")));
+        assert!(!evening.matches(at(Weekday::Sat, "00:00")));
+    }
+
+    #[test]
+    fn match_overnight() {
+        let night = rule("fri 22:00-02:00");
+        assert!(night.matches(at(Weekday::Fri, "22:00")));
+        // The morning part belongs to the previous day
+        assert!(night.matches(at(Weekday::Sat, "01:59")));
+        assert!(!night.matches(at(Weekday::Sat, "02:00")));
+        assert!(!night.matches(at(Weekday::Fri, "01:00")));
+        assert!(!night.matches(at(Weekday::Sat, "22:00")));
+
+        // Sunday night continues on Monday morning
+        let weekend = rule("sun 23:00-01:00");
+        assert!(weekend.matches(at(Weekday::Mon, "00:30")));
+    }
+}
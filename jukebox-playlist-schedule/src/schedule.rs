@@ -0,0 +1,43 @@
+use std::sync::{Arc, Mutex};
+
+use crate::Rule;
+
+/// A playlist active during the time of a rule.
+#[derive(Debug, Clone)]
+pub struct Program<P> {
+    pub name: String,
+    pub rule: Rule,
+    pub playlist: P,
+}
+
+#[derive(Debug, Default)]
+struct ScheduleInner {
+    programs: Vec<(String, String)>,
+    active: String,
+}
+
+/// Programs of a scheduled playlist and the active one, shared with the API.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule {
+    inner: Arc<Mutex<ScheduleInner>>,
+}
+
+impl Schedule {
+    /// Name of the program currently playing.
+    pub fn active(&self) -> String {
+        self.inner.lock().unwrap().active.clone()
+    }
+
+    /// Name and rule of every program, by priority.
+    pub fn programs(&self) -> Vec<(String, String)> {
+        self.inner.lock().unwrap().programs.clone()
+    }
+
+    pub(crate) fn set_programs(&self, programs: Vec<(String, String)>) {
+        self.inner.lock().unwrap().programs = programs;
+    }
+
+    pub(crate) fn set_active(&self, name: &str) {
+        self.inner.lock().unwrap().active = name.to_string();
+    }
+}
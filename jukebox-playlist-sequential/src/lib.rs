@@ -0,0 +1,3 @@
+mod sequential;
+
+pub use sequential::{Order, PlaylistSequential as Playlist, Repeat};
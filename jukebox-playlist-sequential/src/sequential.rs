@@ -0,0 +1,318 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use jukebox_library::{Library, LibraryId, Song};
+use jukebox_playlist::{Empty, Playlist, Prefetch, Stream};
+
+/// Sort order of the songs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Order {
+    /// By file path
+    #[default]
+    Path,
+    /// By album, disc and track number, then by file path
+    Album,
+}
+
+/// What to play after the last song.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Repeat {
+    /// Start again from the first song
+    #[default]
+    All,
+    /// Play the current song again, until skipped
+    One,
+    /// Play nothing
+    Stop,
+}
+
+type Filter = Arc<dyn Fn(&Song) -> bool + Send + Sync>;
+
+/// Play the library, or a subset of it, in a deterministic order.
+#[derive(Clone)]
+pub struct PlaylistSequential<T: Library> {
+    order: Order,
+    repeat: Repeat,
+    filter: Option<Filter>,
+
+    songs: Vec<LibraryId>,
+    /// Index in `songs` of the current song, `songs.len()` once stopped
+    position: Option<usize>,
+    /// The following `next` skips the current song
+    skipped: bool,
+    prefetch: Prefetch<LibraryId>,
+    library: T,
+}
+
+impl<T> PlaylistSequential<T>
+where
+    T: Library,
+{
+    pub fn new(library: T) -> Self {
+        Self {
+            order: Default::default(),
+            repeat: Default::default(),
+            filter: None,
+            songs: Default::default(),
+            position: None,
+            skipped: false,
+            prefetch: Default::default(),
+            library,
+        }
+    }
+
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_repeat(mut self, repeat: Repeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Only play the songs matching `filter`, e.g. a single album.
+    pub fn with_filter(mut self, filter: impl Fn(&Song) -> bool + Send + Sync + 'static) -> Self {
+        self.filter = Some(Arc::new(filter));
+        self
+    }
+
+    async fn reload(&mut self) {
+        let mut songs = Vec::new();
+        for id in self.library.ids().await {
+            if let Some(song) = self.library.song(id).await
+                && self.filter.as_ref().is_none_or(|filter| filter(&song))
+            {
+                songs.push(song);
+            }
+        }
+
+        match self.order {
+            Order::Path => songs.sort_by(|a, b| a.path.cmp(&b.path)),
+            Order::Album => songs.sort_by(|a, b| {
+                let key = |s: &Song| {
+                    (
+                        s.metadata.album.clone(),
+                        s.metadata.disc.unwrap_or(1),
+                        s.metadata.track,
+                    )
+                };
+                key(a).cmp(&key(b)).then_with(|| a.path.cmp(&b.path))
+            }),
+        }
+        self.songs = songs.into_iter().map(|song| song.id).collect();
+    }
+
+    /// Index of the song played by the following `next`.
+    async fn upcoming(&mut self) -> Option<usize> {
+        if self.songs.is_empty() {
+            self.reload().await;
+        }
+
+        let index = match (self.position, self.repeat) {
+            (None, _) => 0,
+            (Some(position), Repeat::One) if !self.skipped && position < self.songs.len() => {
+                position
+            }
+            (Some(position), _) if position + 1 < self.songs.len() => position + 1,
+            (Some(_), Repeat::All | Repeat::One) => {
+                self.reload().await;
+                0
+            }
+            (Some(_), Repeat::Stop) => return None,
+        };
+        (index < self.songs.len()).then_some(index)
+    }
+}
+
+impl<T> Playlist for PlaylistSequential<T>
+where
+    T: Library,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        // Skip the songs removed from the library, at most one round
+        for _ in 0..self.songs.len().max(1) {
+            let Some(index) = self.upcoming().await else {
+                break;
+            };
+            self.position = Some(index);
+            let id = self.songs[index];
+            if let Some(stream) = self.prefetch.take_or_get(id, &self.library).await {
+                self.skipped = false;
+                return stream;
+            }
+        }
+
+        self.skipped = false;
+        self.position = Some(self.songs.len());
+        Box::new(Empty)
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        if let Some(position) = self.position
+            && position > 0
+        {
+            self.position = Some(position - 1);
+        }
+        self.rewind().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(&id) = self.position.and_then(|position| self.songs.get(position))
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
+        self.next().await
+    }
+
+    fn skip(&mut self) {
+        self.skipped = true;
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.position
+            .and_then(|position| self.songs.get(position))
+            .copied()
+    }
+
+    async fn prefetch(&mut self) {
+        if let Some(index) = self.upcoming().await {
+            let id = self.songs[index];
+            if self.prefetch.id() != Some(&id)
+                && let Some(stream) = self.library.get(id).await
+            {
+                self.prefetch.set(id, stream);
+            }
+        }
+    }
+}
+
+impl<T> Debug for PlaylistSequential<T>
+where
+    T: Library + Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PlaylistSequential")
+            .field("order", &self.order)
+            .field("repeat", &self.repeat)
+            .field("songs", &self.songs)
+            .field("position", &self.position)
+            .field("library", &self.library)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jukebox_library::Metadata;
+
+    use super::*;
+
+    /// Library of songs given by path, album and track number.
+    #[derive(Clone)]
+    struct Songs(Arc<Vec<Song>>);
+
+    impl Songs {
+        fn new(songs: &[(&str, &str, u32)]) -> Self {
+            let songs = songs
+                .iter()
+                .enumerate()
+                .map(|(id, &(path, album, track))| Song {
+                    id,
+                    path: path.to_string(),
+                    metadata: Metadata {
+                        album: Some(album.to_string()),
+                        track: Some(track),
+                        ..Default::default()
+                    },
+                    stats: Default::default(),
+                })
+                .collect();
+            Self(Arc::new(songs))
+        }
+    }
+
+    impl Library for Songs {
+        async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+            None
+        }
+
+        async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
+            (id < self.0.len()).then(|| Box::new(Empty) as Box<dyn Stream>)
+        }
+
+        async fn ids(&self) -> Vec<LibraryId> {
+            (0..self.0.len()).collect()
+        }
+
+        async fn song(&self, id: LibraryId) -> Option<Song> {
+            self.0.get(id).cloned()
+        }
+    }
+
+    fn songs() -> Songs {
+        Songs::new(&[("c", "A", 1), ("a", "B", 1), ("b", "A", 2)])
+    }
+
+    async fn play(
+        playlist: &mut PlaylistSequential<Songs>,
+        count: usize,
+    ) -> Vec<Option<LibraryId>> {
+        let mut played = Vec::new();
+        for _ in 0..count {
+            playlist.next().await;
+            played.push(playlist.current());
+        }
+        played
+    }
+
+    #[tokio::test]
+    async fn order() {
+        let mut playlist = PlaylistSequential::new(songs());
+        assert_eq!(
+            play(&mut playlist, 4).await,
+            [Some(1), Some(2), Some(0), Some(1)]
+        );
+
+        let mut playlist = PlaylistSequential::new(songs()).with_order(Order::Album);
+        assert_eq!(play(&mut playlist, 3).await, [Some(0), Some(2), Some(1)]);
+    }
+
+    #[tokio::test]
+    async fn repeat_one() {
+        let mut playlist = PlaylistSequential::new(songs()).with_repeat(Repeat::One);
+        assert_eq!(play(&mut playlist, 3).await, [Some(1), Some(1), Some(1)]);
+
+        playlist.skip();
+        assert_eq!(play(&mut playlist, 2).await, [Some(2), Some(2)]);
+    }
+
+    #[tokio::test]
+    async fn repeat_stop() {
+        let mut playlist = PlaylistSequential::new(songs()).with_repeat(Repeat::Stop);
+        assert_eq!(
+            play(&mut playlist, 5).await,
+            [Some(1), Some(2), Some(0), None, None]
+        );
+    }
+
+    #[tokio::test]
+    async fn filter() {
+        let mut playlist = PlaylistSequential::new(songs())
+            .with_filter(|song| song.metadata.album.as_deref() == Some("A"));
+        assert_eq!(play(&mut playlist, 3).await, [Some(2), Some(0), Some(2)]);
+    }
+
+    #[tokio::test]
+    async fn prev_then_next() {
+        let mut playlist = PlaylistSequential::new(songs());
+        play(&mut playlist, 2).await;
+        playlist.prev().await;
+        assert_eq!(playlist.current(), Some(1));
+        assert_eq!(play(&mut playlist, 1).await, [Some(2)]);
+    }
+}
@@ -0,0 +1,3 @@
+mod shuffle;
+
+pub use shuffle::PlaylistShuffle as Playlist;
@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+
+use jukebox_library::{Library, LibraryId};
+use jukebox_playlist::{Empty, Playlist, Prefetch, Stream};
+use rand::{Rng, seq::SliceRandom};
+
+/// Play every song of the library once in a random order before reshuffling.
+#[derive(Debug, Clone)]
+pub struct PlaylistShuffle<T: Library> {
+    current: Option<LibraryId>,
+    /// Current permutation of the library
+    bag: Vec<LibraryId>,
+    /// Index in `bag` of the next song
+    position: usize,
+    known: HashSet<LibraryId>,
+    /// Number of last played songs kept away from the start of a new bag
+    spacing: usize,
+    prefetch: Prefetch<LibraryId>,
+    library: T,
+}
+
+impl<T> PlaylistShuffle<T>
+where
+    T: Library,
+{
+    const SPACING: usize = 10;
+
+    pub fn new(library: T) -> Self {
+        Self {
+            current: None,
+            bag: Default::default(),
+            position: 0,
+            known: Default::default(),
+            spacing: Self::SPACING,
+            prefetch: Default::default(),
+            library,
+        }
+    }
+
+    /// Set the number of last played songs which can't start the next permutation.
+    pub fn with_spacing(mut self, spacing: usize) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Follow the library content: new songs are spread over the rest of the
+    /// bag, removed ones are dropped.
+    async fn refresh(&mut self) {
+        let ids = self.library.ids().await;
+        let available: HashSet<LibraryId> = ids.iter().copied().collect();
+        let mut rng = rand::rng();
+
+        if self.known.len() != available.len() || !self.known.is_subset(&available) {
+            let mut removed_before = 0;
+            let mut index = 0;
+            self.bag.retain(|id| {
+                let keep = available.contains(id);
+                if !keep && index < self.position {
+                    removed_before += 1;
+                }
+                index += 1;
+                keep
+            });
+            self.position -= removed_before;
+        }
+
+        for id in ids {
+            if self.known.insert(id) {
+                let at = rng.random_range(self.position..=self.bag.len());
+                self.bag.insert(at, id);
+            }
+        }
+        self.known = available;
+    }
+
+    fn reshuffle(&mut self) {
+        let recent: HashSet<LibraryId> = self.bag[self.bag.len().saturating_sub(self.spacing)..]
+            .iter()
+            .copied()
+            .collect();
+        let mut rng = rand::rng();
+        self.bag.shuffle(&mut rng);
+        self.position = 0;
+
+        // Keep the last played songs away from the boundary
+        let spacing = self.spacing.min(self.bag.len() / 2);
+        for idx in 0..spacing {
+            if !recent.contains(&self.bag[idx]) {
+                continue;
+            }
+            let candidates: Vec<usize> = (spacing..self.bag.len())
+                .filter(|&other| !recent.contains(&self.bag[other]))
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            let other = candidates[rng.random_range(0..candidates.len())];
+            self.bag.swap(idx, other);
+        }
+    }
+
+    /// Identifier of the song played by the following `next`.
+    async fn upcoming(&mut self) -> Option<LibraryId> {
+        self.refresh().await;
+        if self.position >= self.bag.len() {
+            self.reshuffle();
+        }
+        self.bag.get(self.position).copied()
+    }
+}
+
+impl<T> Playlist for PlaylistShuffle<T>
+where
+    T: Library,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        // Skip the songs which can't be loaded, at most one round
+        let mut tried = 0;
+        while let Some(id) = self.upcoming().await
+            && tried < self.bag.len()
+        {
+            tried += 1;
+            self.position += 1;
+            if let Some(stream) = self.prefetch.take_or_get(id, &self.library).await {
+                self.current = Some(id);
+                return stream;
+            }
+        }
+
+        // Empty library or no readable song
+        Box::new(Empty)
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        if self.position >= 2 {
+            self.position -= 1;
+            self.current = Some(self.bag[self.position - 1]);
+        }
+        self.rewind().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.current
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
+        self.next().await
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.current
+    }
+
+    async fn prefetch(&mut self) {
+        if let Some(id) = self.upcoming().await
+            && self.prefetch.id() != Some(&id)
+            && let Some(stream) = self.library.get(id).await
+        {
+            self.prefetch.set(id, stream);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use jukebox_library::Song;
+
+    use super::*;
+
+    /// Library of the songs `0..count`, which can't be read unless `readable`.
+    #[derive(Clone)]
+    struct Songs {
+        count: Arc<Mutex<usize>>,
+        readable: bool,
+    }
+
+    impl Songs {
+        fn new(count: usize) -> Self {
+            Self {
+                count: Arc::new(Mutex::new(count)),
+                readable: true,
+            }
+        }
+
+        fn resize(&self, count: usize) {
+            *self.count.lock().unwrap() = count;
+        }
+    }
+
+    impl Library for Songs {
+        async fn random(&self) -> Option<(LibraryId, Box<dyn Stream>)> {
+            None
+        }
+
+        async fn get(&self, id: LibraryId) -> Option<Box<dyn Stream>> {
+            (self.readable && id < *self.count.lock().unwrap())
+                .then(|| Box::new(Empty) as Box<dyn Stream>)
+        }
+
+        async fn ids(&self) -> Vec<LibraryId> {
+            (0..*self.count.lock().unwrap()).collect()
+        }
+
+        async fn song(&self, _id: LibraryId) -> Option<Song> {
+            None
+        }
+    }
+
+    async fn play(playlist: &mut PlaylistShuffle<Songs>, count: usize) -> Vec<LibraryId> {
+        let mut played = Vec::new();
+        for _ in 0..count {
+            playlist.next().await;
+            played.push(playlist.current().unwrap());
+        }
+        played
+    }
+
+    #[tokio::test]
+    async fn every_song_once_per_bag() {
+        let mut playlist = PlaylistShuffle::new(Songs::new(10));
+        for _ in 0..5 {
+            let mut played = play(&mut playlist, 10).await;
+            played.sort();
+            assert_eq!(played, (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn no_repeat_within_spacing() {
+        let spacing = 5;
+        let mut playlist = PlaylistShuffle::new(Songs::new(12)).with_spacing(spacing);
+        let played = play(&mut playlist, 12 * 20).await;
+        for window in played.windows(spacing + 1) {
+            let distinct: HashSet<_> = window.iter().collect();
+            assert_eq!(distinct.len(), window.len(), "{window:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn follow_library() {
+        let songs = Songs::new(4);
+        let mut playlist = PlaylistShuffle::new(songs.clone());
+        let mut played = play(&mut playlist, 2).await;
+
+        // New songs join the current bag
+        songs.resize(6);
+        played.extend(play(&mut playlist, 4).await);
+        played.sort();
+        assert_eq!(played, (0..6).collect::<Vec<_>>());
+
+        // Removed songs leave it
+        songs.resize(2);
+        let played = play(&mut playlist, 4).await;
+        assert!(played.iter().all(|&id| id < 2), "{played:?}");
+    }
+
+    #[tokio::test]
+    async fn unreadable_library() {
+        let songs = Songs {
+            readable: false,
+            ..Songs::new(3)
+        };
+        let mut playlist = PlaylistShuffle::new(songs);
+        playlist.next().await;
+        assert_eq!(playlist.current(), None);
+    }
+}
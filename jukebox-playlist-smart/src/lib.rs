@@ -0,0 +1,17 @@
+//! # Smart playlists
+//!
+//! Play the songs of the library matching a metadata query, e.g.
+//! `genre:jazz year:1955..1965 -artist:"Kenny G"`.
+//!
+//! - `Query`: A parsed query, every term must match. A term is `field:value`,
+//!   a bare word searched in every text field, or a negated term prefixed with `-`.
+//!   Text fields (`title`, `artist`, `album`, `genre`, `path`) match a case
+//!   insensitive substring, number fields (`year`, `disc`, `track`, `rating`,
+//!   `plays`) match a value or an inclusive range like `1955..1965`, `..3` or `4..`.
+//! - `Playlist`: A [`Playlist`](jukebox_playlist::Playlist) shuffling the matching songs.
+
+mod query;
+mod smart;
+
+pub use query::{Query, QueryError};
+pub use smart::PlaylistSmart as Playlist;
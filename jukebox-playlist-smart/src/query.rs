@@ -0,0 +1,300 @@
+use std::{error::Error as StdError, fmt::Display, ops::RangeInclusive, str::FromStr};
+
+use jukebox_library::Song;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    UnknownField(String),
+    InvalidRange(String),
+    UnclosedQuote,
+    EmptyTerm,
+}
+
+impl StdError for QueryError {}
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::UnknownField(field) => write!(f, "unknown field '{field}'"),
+            QueryError::InvalidRange(value) => write!(f, "invalid number or range '{value}'"),
+            QueryError::UnclosedQuote => write!(f, "unclosed quote"),
+            QueryError::EmptyTerm => write!(f, "empty term"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextField {
+    Title,
+    Artist,
+    Album,
+    Genre,
+    Path,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberField {
+    Year,
+    Disc,
+    Track,
+    Rating,
+    Plays,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Matcher {
+    /// Bare word searched in every text field
+    Any(String),
+    Text(TextField, String),
+    Number(NumberField, RangeInclusive<u64>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Term {
+    negated: bool,
+    matcher: Matcher,
+}
+
+/// A metadata query, every term must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl TextField {
+    fn value<'a>(&self, song: &'a Song) -> Option<&'a str> {
+        let metadata = &song.metadata;
+        match self {
+            TextField::Title => metadata.title.as_deref(),
+            TextField::Artist => metadata.artist.as_deref(),
+            TextField::Album => metadata.album.as_deref(),
+            TextField::Genre => metadata.genre.as_deref(),
+            TextField::Path => Some(&song.path),
+        }
+    }
+}
+
+impl NumberField {
+    fn value(&self, song: &Song) -> Option<u64> {
+        let metadata = &song.metadata;
+        match self {
+            NumberField::Year => metadata.year.map(u64::from),
+            NumberField::Disc => metadata.disc.map(u64::from),
+            NumberField::Track => metadata.track.map(u64::from),
+            NumberField::Rating => song.stats.rating.map(u64::from),
+            NumberField::Plays => Some(song.stats.plays),
+        }
+    }
+}
+
+impl Matcher {
+    fn matches(&self, song: &Song) -> bool {
+        let contains = |value: Option<&str>, pattern: &str| {
+            value.is_some_and(|value| value.to_lowercase().contains(pattern))
+        };
+        match self {
+            Matcher::Any(pattern) => [
+                TextField::Title,
+                TextField::Artist,
+                TextField::Album,
+                TextField::Genre,
+                TextField::Path,
+            ]
+            .iter()
+            .any(|field| contains(field.value(song), pattern)),
+            Matcher::Text(field, pattern) => contains(field.value(song), pattern),
+            Matcher::Number(field, range) => field.value(song).is_some_and(|v| range.contains(&v)),
+        }
+    }
+}
+
+impl Query {
+    pub fn matches(&self, song: &Song) -> bool {
+        self.terms
+            .iter()
+            .all(|term| term.matcher.matches(song) != term.negated)
+    }
+}
+
+/// Split a query in words, double quotes group words together.
+fn words(query: &str) -> Result<Vec<String>, QueryError> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err(QueryError::UnclosedQuote);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+fn range(value: &str) -> Result<RangeInclusive<u64>, QueryError> {
+    let number = |v: &str, default: u64| match v {
+        "" => Ok(default),
+        v => v
+            .parse()
+            .map_err(|_| QueryError::InvalidRange(value.to_string())),
+    };
+    match value.split_once("..") {
+        Some((start, end)) => Ok(number(start, 0)?..=number(end, u64::MAX)?),
+        None if !value.is_empty() => number(value, 0).map(|v| v..=v),
+        None => Err(QueryError::InvalidRange(value.to_string())),
+    }
+}
+
+impl FromStr for Term {
+    type Err = QueryError;
+
+    fn from_str(word: &str) -> Result<Self, Self::Err> {
+        let (negated, word) = match word.strip_prefix('-') {
+            Some(word) => (true, word),
+            None => (false, word),
+        };
+
+        let matcher = match word.split_once(':') {
+            None if word.is_empty() => return Err(QueryError::EmptyTerm),
+            None => Matcher::Any(word.to_lowercase()),
+            Some((field, value)) => {
+                let text = |field| Ok(Matcher::Text(field, value.to_lowercase()));
+                let number = |field| range(value).map(|range| Matcher::Number(field, range));
+                match field.to_lowercase().as_str() {
+                    "title" => text(TextField::Title),
+                    "artist" => text(TextField::Artist),
+                    "album" => text(TextField::Album),
+                    "genre" => text(TextField::Genre),
+                    "path" => text(TextField::Path),
+                    "year" => number(NumberField::Year),
+                    "disc" => number(NumberField::Disc),
+                    "track" => number(NumberField::Track),
+                    "rating" => number(NumberField::Rating),
+                    "plays" => number(NumberField::Plays),
+                    _ => Err(QueryError::UnknownField(field.to_string())),
+                }?
+            }
+        };
+        Ok(Term { negated, matcher })
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            terms: words(query)?
+                .iter()
+                .map(|word| word.parse())
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jukebox_library::{Metadata, Stats};
+
+    use super::*;
+
+    fn song(artist: &str, genre: &str, year: u16) -> Song {
+        Song {
+            id: 0,
+            path: format!("/music/{artist}.mp3"),
+            metadata: Metadata {
+                artist: Some(artist.to_string()),
+                genre: Some(genre.to_string()),
+                year: Some(year),
+                ..Default::default()
+            },
+            stats: Stats::default(),
+        }
+    }
+
+    fn query(query: &str) -> Query {
+        query.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_terms() {
+        let query = query(r#"genre:Jazz year:1955..1965 -artist:"Kenny G" blue"#);
+        assert_eq!(
+            query.terms,
+            [
+                Term {
+                    negated: false,
+                    matcher: Matcher::Text(TextField::Genre, "jazz".to_string()),
+                },
+                Term {
+                    negated: false,
+                    matcher: Matcher::Number(NumberField::Year, 1955..=1965),
+                },
+                Term {
+                    negated: true,
+                    matcher: Matcher::Text(TextField::Artist, "kenny g".to_string()),
+                },
+                Term {
+                    negated: false,
+                    matcher: Matcher::Any("blue".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_ranges() {
+        assert_eq!(range("1960"), Ok(1960..=1960));
+        assert_eq!(range("..3"), Ok(0..=3));
+        assert_eq!(range("4.."), Ok(4..=u64::MAX));
+        assert_eq!(range(".."), Ok(0..=u64::MAX));
+        assert!(range("").is_err());
+        assert!(range("a..b").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |query: &str| query.parse::<Query>().unwrap_err();
+        assert_eq!(
+            error("mood:calm"),
+            QueryError::UnknownField("mood".to_string())
+        );
+        assert_eq!(
+            error("year:late"),
+            QueryError::InvalidRange("late".to_string())
+        );
+        assert_eq!(error(r#"artist:"Miles"#), QueryError::UnclosedQuote);
+        assert_eq!(error("-"), QueryError::EmptyTerm);
+        assert_eq!(query("  ").terms, []);
+    }
+
+    #[test]
+    fn match_every_term() {
+        let query = query(r#"genre:jazz year:1955..1965 -artist:"kenny g""#);
+        assert!(query.matches(&song("Miles Davis", "Jazz", 1959)));
+        assert!(!query.matches(&song("Miles Davis", "Jazz", 1970)));
+        assert!(!query.matches(&song("Kenny G", "Jazz", 1960)));
+        assert!(!query.matches(&song("Miles Davis", "Rock", 1959)));
+    }
+
+    #[test]
+    fn match_missing_fields() {
+        let mut untagged = song("", "", 0);
+        untagged.metadata = Metadata::default();
+        // Unknown values never match, even a whole range
+        assert!(!query("year:..").matches(&untagged));
+        assert!(query("-year:..").matches(&untagged));
+        assert!(query("plays:0").matches(&untagged));
+        // Bare words also search the path
+        assert!(query("MUSIC").matches(&untagged));
+    }
+}
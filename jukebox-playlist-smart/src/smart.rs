@@ -0,0 +1,151 @@
+use std::collections::{HashSet, VecDeque};
+
+use jukebox_library::{Library, LibraryId};
+use jukebox_playlist::{Empty, Playlist, Prefetch, Stream};
+use rand::seq::SliceRandom;
+
+use crate::Query;
+
+/// Shuffle the songs matching a query, the query is evaluated again at each
+/// round and whenever the library content changes.
+#[derive(Debug, Clone)]
+pub struct PlaylistSmart<T: Library> {
+    query: Query,
+    /// Library content when the query was last evaluated
+    known: Vec<LibraryId>,
+    /// Number of songs matching the query
+    matches: usize,
+    /// Songs left in the current round, the next one at the top
+    remaining: Vec<LibraryId>,
+    /// Played songs of the current round
+    played: HashSet<LibraryId>,
+    current: Option<LibraryId>,
+    history: VecDeque<LibraryId>,
+    prefetch: Prefetch<LibraryId>,
+    library: T,
+}
+
+impl<T> PlaylistSmart<T>
+where
+    T: Library,
+{
+    const HISTORY_SIZE: usize = 100;
+
+    pub fn new(query: Query, library: T) -> Self {
+        Self {
+            query,
+            known: Default::default(),
+            matches: 0,
+            remaining: Default::default(),
+            played: Default::default(),
+            current: None,
+            history: Default::default(),
+            prefetch: Default::default(),
+            library,
+        }
+    }
+
+    async fn evaluate(&self, ids: &[LibraryId]) -> Vec<LibraryId> {
+        let mut songs = Vec::new();
+        for &id in ids {
+            if let Some(song) = self.library.song(id).await
+                && self.query.matches(&song)
+            {
+                songs.push(id);
+            }
+        }
+        songs
+    }
+
+    /// Identifier of the song played by the following `next`.
+    async fn upcoming(&mut self) -> Option<LibraryId> {
+        let ids = self.library.ids().await;
+        let changed = ids != self.known;
+        if changed || self.remaining.is_empty() {
+            let matches = self.evaluate(&ids).await;
+            self.known = ids;
+            self.matches = matches.len();
+            if self.remaining.is_empty() && !changed {
+                self.played.clear();
+            }
+
+            let mut remaining: Vec<LibraryId> = matches
+                .iter()
+                .filter(|id| !self.played.contains(id))
+                .copied()
+                .collect();
+            if remaining.is_empty() {
+                // New round
+                self.played.clear();
+                remaining = matches;
+            }
+            remaining.shuffle(&mut rand::rng());
+            // Don't play the current song twice in a row at a round boundary
+            if remaining.len() > 1 && remaining.last() == self.current.as_ref() {
+                let last = remaining.len() - 1;
+                remaining.swap(0, last);
+            }
+            self.remaining = remaining;
+        }
+        self.remaining.last().copied()
+    }
+}
+
+impl<T> Playlist for PlaylistSmart<T>
+where
+    T: Library,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        // Skip the songs which can't be loaded, at most one round
+        let mut tried = 0;
+        while let Some(id) = self.upcoming().await
+            && tried < self.matches
+        {
+            tried += 1;
+            self.remaining.pop();
+            self.played.insert(id);
+            if let Some(stream) = self.prefetch.take_or_get(id, &self.library).await {
+                if let Some(current) = self.current.replace(id) {
+                    self.history.push_back(current);
+                    if self.history.len() > Self::HISTORY_SIZE {
+                        self.history.pop_front();
+                    }
+                }
+                return stream;
+            }
+        }
+
+        // Nothing matches the query or no matching song is readable
+        Box::new(Empty)
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.history.pop_back() {
+            self.current = Some(id);
+        }
+        self.rewind().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.current
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
+        self.next().await
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.current
+    }
+
+    async fn prefetch(&mut self) {
+        if let Some(id) = self.upcoming().await
+            && self.prefetch.id() != Some(&id)
+            && let Some(stream) = self.library.get(id).await
+        {
+            self.prefetch.set(id, stream);
+        }
+    }
+}
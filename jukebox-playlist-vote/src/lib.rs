@@ -0,0 +1,5 @@
+mod playlist;
+mod votes;
+
+pub use playlist::PlaylistVote as Playlist;
+pub use votes::{Skip, SkipThreshold, Vote, Votes};
@@ -0,0 +1,6 @@
+use jukebox_playlist_queue::Playlist as PlaylistQueue;
+
+use crate::Votes;
+
+/// Play the queued songs by decreasing score, then fall back to the inner playlist.
+pub type PlaylistVote<P, L> = PlaylistQueue<P, L, Votes>;
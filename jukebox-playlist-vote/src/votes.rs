@@ -0,0 +1,180 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use jukebox_playlist_queue::{Queue, QueueId, QueueItem, Ranking};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+/// Number of skip votes needed to skip the current song.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipThreshold {
+    Absolute(usize),
+    /// Fraction of the current listeners
+    Fraction(f32),
+}
+
+/// State of the vote to skip the current song.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Skip {
+    pub votes: usize,
+    pub required: usize,
+}
+
+impl Skip {
+    pub fn reached(&self) -> bool {
+        self.votes >= self.required
+    }
+}
+
+impl Default for SkipThreshold {
+    fn default() -> Self {
+        Self::Fraction(0.5)
+    }
+}
+
+impl SkipThreshold {
+    fn required(&self, listeners: usize) -> usize {
+        match *self {
+            SkipThreshold::Absolute(votes) => votes,
+            SkipThreshold::Fraction(fraction) => (fraction * listeners as f32).ceil() as usize,
+        }
+        .max(1)
+    }
+}
+
+#[derive(Debug, Default)]
+struct VotesInner {
+    /// Votes on queued songs by user
+    queue: HashMap<QueueId, HashMap<String, Vote>>,
+    /// Users voting to skip the current song
+    skip: HashSet<String>,
+}
+
+/// Votes of the users on a queue, shared between a channel playlist and the API.
+#[derive(Debug, Clone)]
+pub struct Votes {
+    queue: Queue,
+    inner: Arc<Mutex<VotesInner>>,
+    threshold: SkipThreshold,
+}
+
+impl Votes {
+    pub fn new(queue: Queue, threshold: SkipThreshold) -> Self {
+        Self {
+            queue,
+            inner: Default::default(),
+            threshold,
+        }
+    }
+
+    pub fn queue(&self) -> &Queue {
+        &self.queue
+    }
+
+    /// Cast or replace the vote of a user on a queued song, returns its new score.
+    pub fn vote(&self, id: QueueId, user: impl Into<String>, vote: Vote) -> Option<i64> {
+        let queued = self.queue.list();
+        if !queued.iter().any(|item| item.id == id) {
+            return None;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .queue
+            .retain(|id, _| queued.iter().any(|item| item.id == *id));
+        let votes = inner.queue.entry(id).or_default();
+        votes.insert(user.into(), vote);
+        Some(score(votes))
+    }
+
+    /// Queued songs with their score, in play order.
+    pub fn ranked(&self) -> Vec<(QueueItem, i64)> {
+        let inner = self.inner.lock().unwrap();
+        let mut ranked: Vec<_> = self
+            .queue
+            .list()
+            .into_iter()
+            .map(|item| {
+                let score = inner.queue.get(&item.id).map(score).unwrap_or_default();
+                (item, score)
+            })
+            .collect();
+        // Stable sort keeps the queue order between equal scores
+        ranked.sort_by(|(_, a), (_, b)| b.cmp(a));
+        ranked
+    }
+
+    /// Vote to skip the current song, `listeners` is the channel audience.
+    ///
+    /// The votes are cleared once the threshold is reached, so a single
+    /// caller sees it reached and skips the song.
+    pub fn skip(&self, user: impl Into<String>, listeners: usize) -> Skip {
+        let mut inner = self.inner.lock().unwrap();
+        inner.skip.insert(user.into());
+        let skip = Skip {
+            votes: inner.skip.len(),
+            required: self.threshold.required(listeners),
+        };
+        if skip.reached() {
+            inner.skip.clear();
+        }
+        skip
+    }
+
+    pub fn skip_state(&self, listeners: usize) -> Skip {
+        Skip {
+            votes: self.inner.lock().unwrap().skip.len(),
+            required: self.threshold.required(listeners),
+        }
+    }
+}
+
+impl Ranking for Votes {
+    fn front(&self) -> Option<QueueItem> {
+        self.ranked().into_iter().next().map(|(item, _)| item)
+    }
+
+    /// Remove the best ranked song from the queue.
+    fn pop(&self) -> Option<QueueItem> {
+        let (item, _) = self.ranked().into_iter().next()?;
+        let item = self.queue.remove(item.id)?;
+        self.inner.lock().unwrap().queue.remove(&item.id);
+        Some(item)
+    }
+
+    /// Skip votes are for the previous song.
+    fn started(&self) {
+        self.inner.lock().unwrap().skip.clear();
+    }
+}
+
+fn score(votes: &HashMap<String, Vote>) -> i64 {
+    votes
+        .values()
+        .map(|vote| match vote {
+            Vote::Up => 1,
+            Vote::Down => -1,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skip_reached_once() {
+        let votes = Votes::new(Queue::default(), SkipThreshold::Absolute(2));
+        assert!(!votes.skip("a", 4).reached());
+        assert!(votes.skip("b", 4).reached());
+        // A late vote starts a new round instead of skipping again
+        assert!(!votes.skip("c", 4).reached());
+        assert_eq!(votes.skip_state(4).votes, 1);
+    }
+}
@@ -0,0 +1,3 @@
+mod weighted;
+
+pub use weighted::{PlaylistWeighted as Playlist, Weights};
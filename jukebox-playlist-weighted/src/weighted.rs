@@ -0,0 +1,232 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+use jukebox_library::{Library, LibraryId, Song};
+use jukebox_playlist::{Empty, Playlist, Prefetch, Stream};
+use rand::Rng;
+
+/// Tuning of the song selection, a weight of 0 disables a criterion.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Weights {
+    /// Favour well rated songs, unrated songs count as 3 stars
+    pub rating: f64,
+    /// Favour songs played less often
+    pub plays: f64,
+    /// Penalise songs played recently
+    pub recency: f64,
+    /// Time after which a played song has no more penalty
+    pub recency_window: Duration,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            rating: 1.0,
+            plays: 0.5,
+            recency: 2.0,
+            recency_window: Duration::from_secs(4 * 3600),
+        }
+    }
+}
+
+impl Weights {
+    /// Keep every song selectable, even the least favoured ones
+    const MIN_WEIGHT: f64 = 1e-6;
+
+    fn weight(&self, song: &Song, now: SystemTime) -> f64 {
+        let stats = &song.stats;
+        let rating = (stats.rating.unwrap_or(3) as f64 / 3.0).powf(self.rating);
+        let plays = (1.0 + stats.plays as f64).powf(-self.plays);
+        let recency = stats
+            .last_played
+            .and_then(|last| now.duration_since(last).ok())
+            .filter(|elapsed| *elapsed < self.recency_window)
+            .map(|elapsed| {
+                (elapsed.as_secs_f64() / self.recency_window.as_secs_f64()).powf(self.recency)
+            })
+            .unwrap_or(1.0);
+        (rating * plays * recency).max(Self::MIN_WEIGHT)
+    }
+}
+
+/// Random selection biased by ratings, play counts and last played time.
+#[derive(Debug, Clone)]
+pub struct PlaylistWeighted<T: Library> {
+    weights: Weights,
+    current: Option<LibraryId>,
+    /// Played songs, the most recent at the back
+    history: VecDeque<LibraryId>,
+    prefetch: Prefetch<LibraryId>,
+    library: T,
+}
+
+impl<T> PlaylistWeighted<T>
+where
+    T: Library,
+{
+    const HISTORY_SIZE: usize = 100;
+
+    pub fn new(library: T) -> Self {
+        Self {
+            weights: Default::default(),
+            current: None,
+            history: Default::default(),
+            prefetch: Default::default(),
+            library,
+        }
+    }
+
+    pub fn with_weights(mut self, weights: Weights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    async fn pick(&self) -> Option<LibraryId> {
+        let now = SystemTime::now();
+        let mut songs = Vec::new();
+        for id in self.library.ids().await {
+            if let Some(song) = self.library.song(id).await {
+                songs.push((id, self.weights.weight(&song, now)));
+            }
+        }
+
+        draw(&songs, &mut rand::rng())
+    }
+}
+
+/// Pick a song with a probability proportional to its weight.
+fn draw(songs: &[(LibraryId, f64)], rng: &mut impl Rng) -> Option<LibraryId> {
+    let total: f64 = songs.iter().map(|(_, weight)| weight).sum();
+    let mut target = rng.random_range(0.0..total.max(f64::MIN_POSITIVE));
+    for (id, weight) in songs.iter() {
+        if target < *weight {
+            return Some(*id);
+        }
+        target -= weight;
+    }
+    songs.last().map(|(id, _)| *id)
+}
+
+impl<T> Playlist for PlaylistWeighted<T>
+where
+    T: Library,
+{
+    async fn next(&mut self) -> Box<dyn Stream> {
+        let next = match self.prefetch.id() {
+            Some(id) => Some(*id),
+            None => self.pick().await,
+        };
+
+        if let Some(id) = next
+            && let Some(stream) = self.prefetch.take_or_get(id, &self.library).await
+        {
+            if let Some(current) = self.current.replace(id) {
+                self.history.push_back(current);
+                if self.history.len() > Self::HISTORY_SIZE {
+                    self.history.pop_front();
+                }
+            }
+            return stream;
+        }
+
+        // Empty library
+        Box::new(Empty)
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.history.pop_back() {
+            self.current = Some(id);
+        }
+        self.rewind().await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        if let Some(id) = self.current
+            && let Some(stream) = self.library.get(id).await
+        {
+            return stream;
+        }
+
+        self.next().await
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        self.current
+    }
+
+    async fn prefetch(&mut self) {
+        if !self.prefetch.is_some()
+            && let Some(id) = self.pick().await
+            && let Some(stream) = self.library.get(id).await
+        {
+            self.prefetch.set(id, stream);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jukebox_library::Stats;
+    use rand::{SeedableRng, rngs::StdRng};
+
+    use super::*;
+
+    fn song(rating: Option<u8>, plays: u64, last_played: Option<SystemTime>) -> Song {
+        Song {
+            id: 0,
+            path: "0.mp3".to_string(),
+            metadata: Default::default(),
+            stats: Stats {
+                rating,
+                plays,
+                last_played,
+            },
+        }
+    }
+
+    #[test]
+    fn weight() {
+        let weights = Weights::default();
+        let now = SystemTime::now();
+        let weight = |song| weights.weight(&song, now);
+
+        assert_eq!(weight(song(None, 0, None)), 1.0);
+        assert!(weight(song(Some(5), 0, None)) > weight(song(Some(1), 0, None)));
+        assert!(weight(song(None, 0, None)) > weight(song(None, 10, None)));
+        let recent = now - Duration::from_secs(60);
+        let old = now - Duration::from_secs(3600);
+        assert!(weight(song(None, 0, Some(old))) > weight(song(None, 0, Some(recent))));
+        assert_eq!(weight(song(None, 0, Some(now))), Weights::MIN_WEIGHT);
+
+        let flat = Weights {
+            rating: 0.0,
+            plays: 0.0,
+            recency: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(flat.weight(&song(Some(1), 10, Some(recent)), now), 1.0);
+    }
+
+    #[test]
+    fn draw_by_weight() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let songs = [(0, 1.0), (1, 4.0), (2, 0.0)];
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            counts[draw(&songs, &mut rng).unwrap()] += 1;
+        }
+
+        assert_eq!(counts[2], 0);
+        assert!(counts[1] > 3 * counts[0], "{counts:?}");
+        assert!(counts[0] > 0, "{counts:?}");
+    }
+
+    #[test]
+    fn draw_empty() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(draw(&[], &mut rng), None);
+        assert_eq!(draw(&[(3, 0.0)], &mut rng), Some(3));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::{BoxFuture, LibraryId, Playlist, Stream};
+
+/// Object safe version of [`Playlist`], implemented by every playlist.
+///
+/// `Box<dyn DynPlaylist>` implements [`Playlist`] again, so playlists picked at
+/// runtime can be decorated or given to a channel.
+pub trait DynPlaylist: Send {
+    fn next(&mut self) -> BoxFuture<'_, Box<dyn Stream>>;
+    fn prev(&mut self) -> BoxFuture<'_, Box<dyn Stream>>;
+    fn rewind(&mut self) -> BoxFuture<'_, Box<dyn Stream>>;
+    fn prefetch(&mut self) -> BoxFuture<'_, ()>;
+    fn skip(&mut self);
+    fn current(&self) -> Option<LibraryId>;
+    fn clone_box(&self) -> Box<dyn DynPlaylist>;
+}
+
+impl<T> DynPlaylist for T
+where
+    T: Playlist + 'static,
+{
+    fn next(&mut self) -> BoxFuture<'_, Box<dyn Stream>> {
+        Box::pin(Playlist::next(self))
+    }
+
+    fn prev(&mut self) -> BoxFuture<'_, Box<dyn Stream>> {
+        Box::pin(Playlist::prev(self))
+    }
+
+    fn rewind(&mut self) -> BoxFuture<'_, Box<dyn Stream>> {
+        Box::pin(Playlist::rewind(self))
+    }
+
+    fn prefetch(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(Playlist::prefetch(self))
+    }
+
+    fn skip(&mut self) {
+        Playlist::skip(self)
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        Playlist::current(self)
+    }
+
+    fn clone_box(&self) -> Box<dyn DynPlaylist> {
+        Box::new(self.clone())
+    }
+}
+
+impl<'a> Clone for Box<dyn DynPlaylist + 'a> {
+    fn clone(&self) -> Self {
+        self.as_ref().clone_box()
+    }
+}
+
+impl<'a> Playlist for Box<dyn DynPlaylist + 'a> {
+    async fn next(&mut self) -> Box<dyn Stream> {
+        DynPlaylist::next(self.as_mut()).await
+    }
+
+    async fn prev(&mut self) -> Box<dyn Stream> {
+        DynPlaylist::prev(self.as_mut()).await
+    }
+
+    async fn rewind(&mut self) -> Box<dyn Stream> {
+        DynPlaylist::rewind(self.as_mut()).await
+    }
+
+    async fn prefetch(&mut self) {
+        DynPlaylist::prefetch(self.as_mut()).await
+    }
+
+    fn skip(&mut self) {
+        DynPlaylist::skip(self.as_mut())
+    }
+
+    fn current(&self) -> Option<LibraryId> {
+        DynPlaylist::current(self.as_ref())
+    }
+}
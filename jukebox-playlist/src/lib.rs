@@ -1,9 +1,34 @@
-#![allow(async_fn_in_trait)]
+use std::future::Future;
 
-pub use jukebox_decoder::Stream;
+pub use jukebox_decoder::{Empty, Stream};
+pub use jukebox_library::{BoxFuture, LibraryId};
 
+mod boxed;
+mod prefetch;
+
+pub use boxed::DynPlaylist;
+pub use prefetch::Prefetch;
+
+/// Implementations can use `async fn`, the futures must be `Send`.
 pub trait Playlist: Clone + Send {
-    async fn next(&mut self) -> Box<dyn Stream>;
-    async fn prev(&mut self) -> Box<dyn Stream>;
-    async fn rewind(&mut self) -> Box<dyn Stream>;
+    fn next(&mut self) -> impl Future<Output = Box<dyn Stream>> + Send;
+    fn prev(&mut self) -> impl Future<Output = Box<dyn Stream>> + Send;
+    fn rewind(&mut self) -> impl Future<Output = Box<dyn Stream>> + Send;
+    /// Load the stream returned by the following `next` so the switch doesn't wait on the library.
+    fn prefetch(&mut self) -> impl Future<Output = ()> + Send {
+        async {}
+    }
+    /// The following `next` skips the current song instead of following its
+    /// end, e.g. to not repeat it.
+    fn skip(&mut self) {}
+    /// Song returned by the last `next`, `prev` or `rewind`.
+    fn current(&self) -> Option<LibraryId>;
+
+    /// Erase the type of the playlist, to select it at runtime.
+    fn boxed(self) -> Box<dyn DynPlaylist>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(self)
+    }
 }
@@ -0,0 +1,67 @@
+use std::fmt::{self, Debug, Formatter};
+
+use jukebox_library::Library;
+
+use crate::{LibraryId, Stream};
+
+/// A stream loaded ahead of time by a playlist, tagged with its identifier.
+///
+/// A clone of a playlist starts without any prefetched stream.
+pub struct Prefetch<K> {
+    inner: Option<(K, Box<dyn Stream>)>,
+}
+
+impl<K> Prefetch<K> {
+    pub fn set(&mut self, id: K, stream: Box<dyn Stream>) {
+        self.inner = Some((id, stream));
+    }
+
+    pub fn take(&mut self) -> Option<(K, Box<dyn Stream>)> {
+        self.inner.take()
+    }
+
+    pub fn id(&self) -> Option<&K> {
+        self.inner.as_ref().map(|(id, _)| id)
+    }
+
+    pub fn is_some(&self) -> bool {
+        self.inner.is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.inner = None;
+    }
+}
+
+impl Prefetch<LibraryId> {
+    /// The prefetched stream if it is the song `id`, else the song loaded
+    /// from the library.
+    pub async fn take_or_get(
+        &mut self,
+        id: LibraryId,
+        library: &impl Library,
+    ) -> Option<Box<dyn Stream>> {
+        match self.id() {
+            Some(prefetch) if *prefetch == id => self.take().map(|(_, stream)| stream),
+            _ => library.get(id).await,
+        }
+    }
+}
+
+impl<K> Default for Prefetch<K> {
+    fn default() -> Self {
+        Self { inner: None }
+    }
+}
+
+impl<K> Clone for Prefetch<K> {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl<K: Debug> Debug for Prefetch<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Prefetch").field(&self.id()).finish()
+    }
+}
@@ -0,0 +1,190 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use jukebox_channel::{ChannelConfig, IcecastConfig, Plays, StreamConfig, Titles};
+use jukebox_decoder::Format;
+use jukebox_library::Library as _;
+use jukebox_playlist_jingle::{Insertion, Playlist as PlaylistJingle, Rules as JingleRules};
+use jukebox_playlist_queue::Queue;
+use jukebox_playlist_schedule::{Playlist as PlaylistSchedule, Program, Schedule};
+use jukebox_playlist_vote::{Playlist as PlaylistVote, Votes};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::info;
+
+use crate::{
+    Library,
+    config::{ChannelSection, ConfigError},
+    playlist::{self, ChannelPlaylist},
+};
+
+/// A configured channel with the handles shared with the API.
+pub(crate) struct Channel {
+    pub playlist: ChannelPlaylist,
+    pub config: ChannelConfig,
+    pub mount: String,
+    pub library: Library,
+    pub queue: Queue,
+    pub votes: Votes,
+    pub schedule: Schedule,
+}
+
+impl Channel {
+    /// Build a channel from a validated configuration, keeping the queue and
+    /// votes of a previous configuration when given.
+    pub(crate) fn new(
+        name: &str,
+        section: &ChannelSection,
+        libraries: &HashMap<String, Library>,
+        votes: Option<Votes>,
+    ) -> Result<Self, ConfigError> {
+        let playlist_error = |error| ConfigError::Playlist {
+            channel: name.to_string(),
+            error,
+        };
+        let library = libraries[&section.library].clone();
+        let format = library.format();
+        // Segmented outputs wrap MP3 frames
+        let outputs = [("hls", &section.hls), ("dash", &section.dash)];
+        if let Some((output, _)) = outputs
+            .into_iter()
+            .find(|(_, section)| section.is_some() && format != Format::MP3)
+        {
+            return Err(ConfigError::UnsupportedFormat {
+                channel: name.to_string(),
+                output,
+                format: format.mime,
+            });
+        }
+
+        let mut programs = Vec::new();
+        for program in &section.schedule {
+            programs.push(Program {
+                name: program.name.clone(),
+                rule: program.rule.0.clone(),
+                playlist: playlist::new(&program.playlist, &library).map_err(playlist_error)?,
+            });
+        }
+        let schedule = Schedule::default();
+        let default = playlist::new(&section.playlist, &library).map_err(playlist_error)?;
+
+        let votes = votes
+            .unwrap_or_else(|| Votes::new(Queue::new(section.queue_limit), section.skip_threshold));
+        let queue = votes.queue().clone();
+
+        if let Some(jingles) = &section.jingles
+            && libraries[&jingles.library].format() != format
+        {
+            return Err(ConfigError::FormatMismatch {
+                channel: name.to_string(),
+                library: jingles.library.clone(),
+            });
+        }
+        let (jingles, rules) = match &section.jingles {
+            Some(jingles) => (
+                libraries[&jingles.library].clone(),
+                JingleRules {
+                    tracks: jingles.tracks,
+                    interval: jingles
+                        .minutes
+                        .map(|minutes| Duration::from_secs(minutes * 60)),
+                },
+            ),
+            None => (
+                jukebox_library_file::Builder::new().build(),
+                JingleRules::default(),
+            ),
+        };
+
+        let playlist = PlaylistJingle::new(
+            PlaylistVote::new(
+                PlaylistSchedule::new(default, programs, schedule.clone()),
+                library.clone(),
+                votes.clone(),
+            ),
+            jingles,
+            rules,
+        );
+        log_insertions(name.to_string(), playlist.subscribe());
+
+        Ok(Self {
+            playlist,
+            config: ChannelConfig {
+                mode: section.mode,
+                format: Some(format),
+                burst: Duration::from_secs(section.burst),
+                stream: StreamConfig {
+                    capacity: section.stream.buffer,
+                    policy: section.stream.slow_client,
+                },
+                hls: section.hls.as_ref().map(Into::into),
+                dash: section.dash.as_ref().map(Into::into),
+                icecast: section
+                    .icecast
+                    .iter()
+                    .map(|icecast| IcecastConfig {
+                        address: icecast.url.address.clone(),
+                        mount: icecast.url.mount.clone(),
+                        user: icecast.url.user.clone(),
+                        password: icecast.url.password.clone(),
+                        protocol: icecast.protocol,
+                        name: icecast
+                            .name
+                            .clone()
+                            .or_else(|| section.station.name.clone()),
+                        description: icecast
+                            .description
+                            .clone()
+                            .or_else(|| section.station.description.clone()),
+                        genre: icecast
+                            .genre
+                            .clone()
+                            .or_else(|| section.station.genre.clone()),
+                        public: icecast.public.unwrap_or(section.station.public),
+                    })
+                    .collect(),
+                titles: Some(titles(&library)),
+                plays: Some(plays(&library)),
+            },
+            mount: section.mount(name),
+            library,
+            queue,
+            votes,
+            schedule,
+        })
+    }
+}
+
+/// Titles of the songs of the channel library.
+fn titles(library: &Library) -> Titles {
+    let library = library.clone();
+    Titles(Arc::new(move |id| {
+        let library = library.clone();
+        Box::pin(async move { library.song(id).await.map(|song| song.title()) })
+    }))
+}
+
+/// Count the plays of the songs streamed by the channel in its library.
+fn plays(library: &Library) -> Plays {
+    let library = library.clone();
+    Plays(Arc::new(move |id| {
+        let library = library.clone();
+        Box::pin(async move { library.played(id).await })
+    }))
+}
+
+/// Log the jingles inserted in a channel.
+fn log_insertions(name: String, mut insertions: broadcast::Receiver<Insertion>) {
+    tokio::spawn(async move {
+        loop {
+            match insertions.recv().await {
+                Ok(insertion) => info!(
+                    "jingle: {} insert {:?} ({:?}) at {:?}",
+                    name, insertion.path, insertion.reason, insertion.time
+                ),
+                Err(RecvError::Lagged(missed)) => {
+                    info!("jingle: {} {} insertions not logged", name, missed)
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
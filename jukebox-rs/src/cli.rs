@@ -1,15 +1,66 @@
 use clap::Parser;
+use jukebox_channel::SlowClientPolicy;
+use jukebox_playlist_vote::SkipThreshold;
 
+/// Options given on the command line or in the environment override the
+/// configuration file, channel options apply to every channel.
 #[derive(Parser, Debug)]
 #[command(name = "Jukebox")]
 #[command(author = "Author Name <lilian-code@maurel.biz>")]
 #[command(version = "1.0")]
 #[command(about = "Jukebox application")]
 pub(crate) struct Cli {
-    /// Listen port
-    #[arg(short, long, default_value = "8080", env = "PORT")]
-    pub port: u16,
-    /// List of file URLs
+    /// Configuration file
+    #[arg(short = 'C', long, env = "JUKEBOX_CONFIG")]
+    pub config: Option<String>,
+    /// Listen port, on every address
+    #[arg(short, long, env = "PORT")]
+    pub port: Option<u16>,
+    /// List of file URLs of the default library
     #[arg(short, long)]
     pub file_urls: Vec<String>,
+    /// Keep the channels playing when nobody listens
+    #[arg(short, long, env = "CONTINUOUS")]
+    pub continuous: bool,
+    /// Seconds of audio sent to a listener when it connects
+    #[arg(short, long, env = "BURST")]
+    pub burst: Option<u64>,
+    /// Maximum number of songs queued by a user
+    #[arg(long, env = "QUEUE_LIMIT")]
+    pub queue_limit: Option<usize>,
+    /// Skip votes needed to skip a song: a number of votes or a percentage of listeners
+    #[arg(long, env = "SKIP_THRESHOLD", value_parser = parse_skip_threshold)]
+    pub skip_threshold: Option<SkipThreshold>,
+    /// Frames kept for the listeners, a slower listener loses the oldest ones
+    #[arg(long, env = "LISTENER_BUFFER")]
+    pub listener_buffer: Option<usize>,
+    /// Slow listener policy: drop-oldest, skip-half or disconnect[:<dropped frames>]
+    #[arg(long, env = "SLOW_CLIENT", value_parser = parse_slow_client)]
+    pub slow_client: Option<SlowClientPolicy>,
+}
+
+pub(crate) fn parse_slow_client(value: &str) -> Result<SlowClientPolicy, String> {
+    match value.split_once(':') {
+        None if value == "drop-oldest" => Ok(SlowClientPolicy::DropOldest),
+        None if value == "skip-half" => Ok(SlowClientPolicy::SkipHalf),
+        None if value == "disconnect" => Ok(SlowClientPolicy::Disconnect { dropped: 0 }),
+        Some(("disconnect", dropped)) => dropped
+            .parse()
+            .map(|dropped| SlowClientPolicy::Disconnect { dropped })
+            .map_err(|e| format!("invalid dropped frames threshold: {e}")),
+        _ => Err(format!("unknown slow client policy '{value}'")),
+    }
+}
+
+pub(crate) fn parse_skip_threshold(value: &str) -> Result<SkipThreshold, String> {
+    match value.strip_suffix('%') {
+        Some(percent) => percent
+            .parse::<f32>()
+            .map(|percent| SkipThreshold::Fraction(percent / 100.0))
+            .map_err(|e| format!("invalid percentage: {e}")),
+        None => value
+            .parse()
+            .map(SkipThreshold::Absolute)
+            .map_err(|e| format!("invalid number of votes: {e}")),
+    }
 }
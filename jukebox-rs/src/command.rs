@@ -1,20 +1,44 @@
-use actix_web::{HttpResponse, HttpResponseBuilder, Responder, http::StatusCode, web};
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder, http::StatusCode, web};
 use jukebox_channel::ChannelCommand;
 
-pub(crate) async fn api_next(channel_manager: web::Data<ChannelCommand>) -> impl Responder {
+use crate::stream::{DEFAULT_MOUNT, Mounts};
+
+/// Channel named in the request path, or the one on the default mount.
+fn channel(request: &HttpRequest, mounts: &Mounts) -> Option<String> {
+    request
+        .match_info()
+        .get("name")
+        .map(str::to_string)
+        .or_else(|| mounts.get(DEFAULT_MOUNT))
+}
+
+pub(crate) async fn api_next(
+    request: HttpRequest,
+    mounts: web::Data<Mounts>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(name) = channel(&request, &mounts) else {
+        return HttpResponse::NotFound().finish();
+    };
     let mut builder = HttpResponseBuilder::new(StatusCode::NO_CONTENT);
     channel_manager
-        .next("test")
+        .next(name)
         .await
         .map(|_| builder.finish())
         .unwrap_or(HttpResponse::InternalServerError().finish())
 }
 
-pub(crate) async fn api_previous(channel_manager: web::Data<ChannelCommand>) -> impl Responder {
+pub(crate) async fn api_previous(
+    request: HttpRequest,
+    mounts: web::Data<Mounts>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(name) = channel(&request, &mounts) else {
+        return HttpResponse::NotFound().finish();
+    };
     let mut builder = HttpResponseBuilder::new(StatusCode::NO_CONTENT);
-    builder.content_type("audio/mpg");
     channel_manager
-        .previous("test")
+        .previous(name)
         .await
         .map(|_| builder.finish())
         .unwrap_or(HttpResponse::InternalServerError().finish())
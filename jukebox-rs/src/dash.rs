@@ -0,0 +1,438 @@
+//! DASH output of the channels, as CMAF segments of fragmented MP4.
+//!
+//! The segment being produced is sent while it grows, one `moof`/`mdat`
+//! chunk each time the channel publishes frames, so players can stay close
+//! to the live edge.
+
+use std::{fmt::Write, time::SystemTime};
+
+use actix_web::{HttpResponse, Responder, http::header, web};
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
+use jukebox_channel::{ChannelCommand, ChannelStatus, Segments};
+use jukebox_decoder::Frame;
+
+const MANIFEST_CONTENT_TYPE: &str = "application/dash+xml";
+const SEGMENT_CONTENT_TYPE: &str = "audio/mp4";
+/// Every MP3 sample rate divides it, so frame durations are exact
+const TIMESCALE: u64 = 14_112_000;
+/// How long before its end a segment can be requested, the channel
+/// publishes frames every 100 ms
+const CHUNK_SECONDS: f64 = 0.1;
+
+/// Segments of a channel, `None` if the channel is unknown or without DASH.
+async fn segments(name: &str, channel_manager: &ChannelCommand) -> Option<Segments> {
+    channel_manager.dash(name).await.ok().flatten()
+}
+
+/// A frame giving the audio format of the output, waiting for the first
+/// one of a channel started by this request.
+async fn first_frame(segments: &Segments) -> Option<Frame> {
+    let mut changes = segments.changes();
+    let wait = tokio::time::sleep(segments.config().segment * 2);
+    tokio::pin!(wait);
+    loop {
+        let frame = match segments.window().segments.last() {
+            Some(segment) => segment.frames.first().cloned(),
+            None => segments
+                .progress(segments.next(), 0)
+                .and_then(|progress| progress.frames.into_iter().next()),
+        };
+        if frame.is_some() {
+            return frame;
+        }
+        tokio::select! {
+            changed = changes.changed() => changed.ok()?,
+            _ = &mut wait => return None,
+        }
+    }
+}
+
+/// MPEG-1 layer III, or MPEG-2 layer III for the low sample rates.
+fn object_type(frame: &Frame) -> u8 {
+    match frame.sample_rate {
+        32_000.. => 0x6B,
+        _ => 0x69,
+    }
+}
+
+fn channels(frame: &Frame) -> u16 {
+    // Channel mode of the frame header, 3 is mono
+    match frame.data.get(3).map(|b| b >> 6) {
+        Some(3) => 1,
+        _ => 2,
+    }
+}
+
+/// Bitrate of a frame, in bit/s.
+fn bitrate(frame: &Frame) -> u64 {
+    match frame.nb_samples {
+        0 => 0,
+        samples => frame.data.len() as u64 * 8 * frame.sample_rate as u64 / samples as u64,
+    }
+}
+
+fn ticks(frame: &Frame) -> u64 {
+    match frame.sample_rate {
+        0 => 0,
+        rate => frame.nb_samples as u64 * TIMESCALE / rate as u64,
+    }
+}
+
+fn ticks_from_duration(duration: std::time::Duration) -> u64 {
+    (duration.as_micros() * TIMESCALE as u128 / 1_000_000) as u64
+}
+
+fn time(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Live manifest of a channel, listing the segments of the window.
+pub(crate) async fn api_manifest(
+    name: web::Path<String>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(segments) = segments(&name, &channel_manager).await else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(frame) = first_frame(&segments).await else {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, "1"))
+            .finish();
+    };
+
+    // Measured on the channel output, or on a frame while nothing is buffered
+    let bandwidth = match channel_manager.status(name.as_str()).await {
+        Ok(ChannelStatus {
+            bitrate: Some(bitrate),
+            ..
+        }) => bitrate as u64 * 1000,
+        _ => bitrate(&frame),
+    };
+
+    let config = segments.config();
+    let window = segments.window();
+    let segment = config.segment.as_secs_f64();
+    let mut timeline = String::new();
+    for segment in &window.segments {
+        let start = ticks_from_duration(segment.start);
+        let end = ticks_from_duration(segment.start + segment.duration);
+        let _ = write!(timeline, r#"<S t="{}" d="{}"/>"#, start, end - start);
+    }
+
+    let mut manifest = String::new();
+    let _ = writeln!(manifest, r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    let _ = writeln!(
+        manifest,
+        r#"<MPD xmlns="urn:mpeg:dash:schema:mpd:2011" profiles="urn:mpeg:dash:profile:isoff-live:2011,urn:mpeg:dash:profile:cmaf:2019" type="dynamic" availabilityStartTime="{}" publishTime="{}" minimumUpdatePeriod="PT{segment}S" minBufferTime="PT{segment}S" timeShiftBufferDepth="PT{}S">"#,
+        time(segments.origin()),
+        time(SystemTime::now()),
+        segment * config.window as f64,
+    );
+    let _ = writeln!(
+        manifest,
+        r#"<ServiceDescription id="0"><Latency target="{}"/></ServiceDescription>"#,
+        (segment * 1000.0) as u64
+    );
+    let _ = writeln!(manifest, r#"<Period id="0" start="PT0S">"#);
+    let _ = writeln!(
+        manifest,
+        r#"<AdaptationSet contentType="audio" mimeType="{SEGMENT_CONTENT_TYPE}" segmentAlignment="true" lang="und">"#
+    );
+    let _ = writeln!(
+        manifest,
+        r#"<Representation id="audio" codecs="mp4a.{:X}" bandwidth="{}" audioSamplingRate="{}">"#,
+        object_type(&frame),
+        bandwidth,
+        frame.sample_rate
+    );
+    let _ = writeln!(
+        manifest,
+        r#"<AudioChannelConfiguration schemeIdUri="urn:mpeg:dash:23003:3:audio_channel_configuration:2011" value="{}"/>"#,
+        channels(&frame)
+    );
+    let _ = writeln!(
+        manifest,
+        r#"<SegmentTemplate timescale="{TIMESCALE}" initialization="init.mp4" media="$Number$.m4s" startNumber="{}" availabilityTimeOffset="{:.1}" availabilityTimeComplete="false">"#,
+        window
+            .segments
+            .first()
+            .map(|s| s.sequence)
+            .unwrap_or(segments.next()),
+        (segment - CHUNK_SECONDS).max(0.0)
+    );
+    let _ = writeln!(manifest, "<SegmentTimeline>{timeline}</SegmentTimeline>");
+    let _ = writeln!(manifest, "</SegmentTemplate>");
+    let _ = writeln!(manifest, "</Representation>");
+    let _ = writeln!(manifest, "</AdaptationSet>");
+    let _ = writeln!(manifest, "</Period>");
+    let _ = writeln!(
+        manifest,
+        r#"<UTCTiming schemeIdUri="urn:mpeg:dash:utc:direct:2014" value="{}"/>"#,
+        time(SystemTime::now())
+    );
+    let _ = writeln!(manifest, "</MPD>");
+
+    HttpResponse::Ok()
+        .content_type(MANIFEST_CONTENT_TYPE)
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .body(manifest)
+}
+
+/// Initialization segment describing the audio track.
+pub(crate) async fn api_init(
+    name: web::Path<String>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(segments) = segments(&name, &channel_manager).await else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(frame) = first_frame(&segments).await else {
+        return HttpResponse::ServiceUnavailable()
+            .insert_header((header::RETRY_AFTER, "1"))
+            .finish();
+    };
+
+    HttpResponse::Ok()
+        .content_type(SEGMENT_CONTENT_TYPE)
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .body(init(&frame))
+}
+
+struct Chunks {
+    segments: Segments,
+    changes: tokio::sync::watch::Receiver<()>,
+    sequence: u64,
+    /// Index of the next frame to send
+    from: usize,
+    /// Decode time of the next frame
+    time: Option<u64>,
+    complete: bool,
+}
+
+/// A media segment, sent chunk by chunk while it is produced.
+pub(crate) async fn api_segment(
+    path: web::Path<(String, u64)>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let (name, sequence) = path.into_inner();
+    let Some(segments) = segments(&name, &channel_manager).await else {
+        return HttpResponse::NotFound().finish();
+    };
+    let changes = segments.changes();
+    let Some(progress) = segments.progress(sequence, 0) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let mut response = HttpResponse::Ok();
+    response.content_type(SEGMENT_CONTENT_TYPE);
+    if progress.complete {
+        let config = segments.config();
+        let time = ticks_from_duration(progress.start);
+        let mut body = styp();
+        body.extend_from_slice(&chunk(progress.frame, time, &progress.frames));
+        return response
+            .insert_header(header::CacheControl(vec![header::CacheDirective::MaxAge(
+                (config.segment * config.window as u32).as_secs() as u32,
+            )]))
+            .body(body);
+    }
+
+    let chunks = Chunks {
+        segments,
+        changes,
+        sequence,
+        from: 0,
+        time: None,
+        complete: false,
+    };
+    let stream = futures::stream::unfold(chunks, |mut chunks| async move {
+        loop {
+            if chunks.complete {
+                return None;
+            }
+            let progress = chunks.segments.progress(chunks.sequence, chunks.from)?;
+            chunks.complete = progress.complete;
+            if !progress.frames.is_empty() {
+                let mut data = Vec::new();
+                let time = match chunks.time {
+                    Some(time) => time,
+                    None => {
+                        data = styp();
+                        ticks_from_duration(progress.start)
+                    }
+                };
+                data.extend_from_slice(&chunk(progress.frame, time, &progress.frames));
+                chunks.from += progress.frames.len();
+                chunks.time = Some(time + progress.frames.iter().map(ticks).sum::<u64>());
+                return Some((Ok::<_, actix_web::Error>(Bytes::from(data)), chunks));
+            }
+            chunks.changes.changed().await.ok()?;
+        }
+    });
+    response
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .streaming(stream)
+}
+
+fn mp4_box(kind: &[u8; 4], parts: &[&[u8]]) -> Vec<u8> {
+    let size = 8 + parts.iter().map(|part| part.len()).sum::<usize>();
+    let mut data = Vec::with_capacity(size);
+    data.extend_from_slice(&(size as u32).to_be_bytes());
+    data.extend_from_slice(kind);
+    parts.iter().for_each(|part| data.extend_from_slice(part));
+    data
+}
+
+fn full_box(kind: &[u8; 4], version: u8, flags: u32, parts: &[&[u8]]) -> Vec<u8> {
+    let header = ((version as u32) << 24 | flags).to_be_bytes();
+    mp4_box(kind, &[&[&header[..]], parts].concat())
+}
+
+/// Transformation matrix of the movie and track headers.
+const MATRIX: [u32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000];
+
+fn be32(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+fn init(frame: &Frame) -> Vec<u8> {
+    let ftyp = mp4_box(b"ftyp", &[b"iso6", &[0; 4], b"iso6cmfcdashmp41"]);
+
+    let mvhd = full_box(
+        b"mvhd",
+        0,
+        0,
+        &[
+            &be32(&[0, 0, 1000, 0, 0x10000]),
+            &[0x01, 0x00, 0, 0],
+            &[0; 8],
+            &be32(&MATRIX),
+            &[0; 24],
+            &be32(&[2]),
+        ],
+    );
+    let tkhd = full_box(
+        b"tkhd",
+        0,
+        0x3,
+        &[
+            &be32(&[0, 0, 1, 0, 0]),
+            &[0; 8],
+            &[0, 0, 0, 0, 0x01, 0x00, 0, 0],
+            &be32(&MATRIX),
+            &be32(&[0, 0]),
+        ],
+    );
+    let mdhd = full_box(
+        b"mdhd",
+        0,
+        0,
+        &[&be32(&[0, 0, TIMESCALE as u32, 0]), &[0x55, 0xC4, 0, 0]],
+    );
+    let hdlr = full_box(
+        b"hdlr",
+        0,
+        0,
+        &[&[0; 4], b"soun", &[0; 12], b"SoundHandler\0"],
+    );
+    let smhd = full_box(b"smhd", 0, 0, &[&[0; 4]]);
+    let dinf = mp4_box(
+        b"dinf",
+        &[&full_box(
+            b"dref",
+            0,
+            0,
+            &[&be32(&[1]), &full_box(b"url ", 0, 1, &[])],
+        )],
+    );
+
+    // MP3 needs no decoder specific information
+    let decoder_config = [&[object_type(frame), 0x15, 0, 0, 0][..], &[0; 8]].concat();
+    let es = [
+        &[0, 0, 0][..],
+        &descriptor(0x04, &decoder_config),
+        &descriptor(0x06, &[0x02]),
+    ]
+    .concat();
+    let esds = full_box(b"esds", 0, 0, &[&descriptor(0x03, &es)]);
+    let mp4a = mp4_box(
+        b"mp4a",
+        &[
+            &[0; 6],
+            &1u16.to_be_bytes(),
+            &[0; 8],
+            &channels(frame).to_be_bytes(),
+            &16u16.to_be_bytes(),
+            &[0; 4],
+            &((frame.sample_rate as u32) << 16).to_be_bytes(),
+            &esds,
+        ],
+    );
+    let stbl = mp4_box(
+        b"stbl",
+        &[
+            &full_box(b"stsd", 0, 0, &[&be32(&[1]), &mp4a]),
+            &full_box(b"stts", 0, 0, &[&be32(&[0])]),
+            &full_box(b"stsc", 0, 0, &[&be32(&[0])]),
+            &full_box(b"stsz", 0, 0, &[&be32(&[0, 0])]),
+            &full_box(b"stco", 0, 0, &[&be32(&[0])]),
+        ],
+    );
+    let minf = mp4_box(b"minf", &[&smhd, &dinf, &stbl]);
+    let mdia = mp4_box(b"mdia", &[&mdhd, &hdlr, &minf]);
+    let trak = mp4_box(b"trak", &[&tkhd, &mdia]);
+    let mvex = mp4_box(
+        b"mvex",
+        &[&full_box(b"trex", 0, 0, &[&be32(&[1, 1, 0, 0, 0])])],
+    );
+    let moov = mp4_box(b"moov", &[&mvhd, &trak, &mvex]);
+    [ftyp, moov].concat()
+}
+
+/// MPEG-4 descriptor, the payloads here are shorter than 128 bytes.
+fn descriptor(tag: u8, payload: &[u8]) -> Vec<u8> {
+    [&[tag, payload.len() as u8][..], payload].concat()
+}
+
+fn styp() -> Vec<u8> {
+    mp4_box(b"styp", &[b"msdh", &[0; 4], b"msdhmsixcmfs"])
+}
+
+/// A `moof`/`mdat` pair holding `frames`, the first one being the `frame`
+/// one of the output.
+fn chunk(frame: u64, time: u64, frames: &[Frame]) -> Vec<u8> {
+    let moof = |data_offset: u32| {
+        let samples: Vec<u32> = frames
+            .iter()
+            .flat_map(|frame| [ticks(frame) as u32, frame.data.len() as u32])
+            .collect();
+        let traf = mp4_box(
+            b"traf",
+            &[
+                // Offsets are relative to the moof box
+                &full_box(b"tfhd", 0, 0x02_0000, &[&be32(&[1])]),
+                &full_box(b"tfdt", 1, 0, &[&time.to_be_bytes()]),
+                &full_box(
+                    b"trun",
+                    0,
+                    0x301,
+                    &[&be32(&[frames.len() as u32, data_offset]), &be32(&samples)],
+                ),
+            ],
+        );
+        // Increases with each chunk of the output, chunks hold at least one
+        // frame. It wraps after years of audio.
+        let number = (frame + 1) as u32;
+        mp4_box(
+            b"moof",
+            &[&full_box(b"mfhd", 0, 0, &[&be32(&[number])]), &traf],
+        )
+    };
+    let size = moof(0).len() as u32;
+    let data: Vec<u8> = frames
+        .iter()
+        .flat_map(|frame| frame.data.iter().copied())
+        .collect();
+    [moof(size + 8), mp4_box(b"mdat", &[&data])].concat()
+}
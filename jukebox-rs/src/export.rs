@@ -0,0 +1,99 @@
+use std::ops::Deref;
+
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use jukebox_channel::ChannelCommand;
+use jukebox_library::{Library, LibraryId, Song};
+use jukebox_playlist_file::write_m3u;
+
+use crate::{config::DEFAULT_LIBRARY, queue::Queues, registry::Registry};
+
+const M3U_CONTENT_TYPE: &str = "audio/x-mpegurl; charset=utf-8";
+
+/// Library played by each channel, a distinct type from the libraries by name.
+pub(crate) struct ChannelLibraries<L> {
+    libraries: Registry<L>,
+    /// Name of the library of each channel, to link to its tracks
+    pub names: Registry<String>,
+}
+
+impl<L> ChannelLibraries<L> {
+    pub(crate) fn new(libraries: Registry<L>, names: Registry<String>) -> Self {
+        Self { libraries, names }
+    }
+}
+
+impl<L> Deref for ChannelLibraries<L> {
+    type Target = Registry<L>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.libraries
+    }
+}
+
+/// Songs of a channel library as an M3U playlist linking to their files.
+async fn m3u<L: Library>(
+    request: &HttpRequest,
+    libraries: &ChannelLibraries<L>,
+    name: &str,
+    ids: impl IntoIterator<Item = LibraryId>,
+) -> HttpResponse {
+    let (Some(library), Some(library_name)) = (libraries.get(name), libraries.names.get(name))
+    else {
+        return HttpResponse::NotFound().finish();
+    };
+    let mut songs: Vec<Song> = Vec::new();
+    for id in ids {
+        if let Some(song) = library.song(id).await {
+            songs.push(song);
+        }
+    }
+
+    let connection = request.connection_info();
+    let base = match library_name.as_str() {
+        DEFAULT_LIBRARY => format!(
+            "{}://{}/api/library",
+            connection.scheme(),
+            connection.host()
+        ),
+        _ => format!(
+            "{}://{}/api/libraries/{library_name}",
+            connection.scheme(),
+            connection.host()
+        ),
+    };
+    HttpResponse::Ok()
+        .content_type(M3U_CONTENT_TYPE)
+        .body(write_m3u(&songs, |song| {
+            format!("{base}/tracks/{}/file", song.id)
+        }))
+}
+
+pub(crate) async fn api_history_m3u<L: Library>(
+    request: HttpRequest,
+    name: web::Path<String>,
+    channel_manager: web::Data<ChannelCommand>,
+    libraries: web::Data<ChannelLibraries<L>>,
+) -> impl Responder {
+    if libraries.get(name.as_str()).is_none() {
+        return HttpResponse::NotFound().finish();
+    }
+    match channel_manager.history(name.as_str()).await {
+        Ok(history) => m3u(&request, &libraries, &name, history).await,
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+pub(crate) async fn api_queue_m3u<L: Library>(
+    request: HttpRequest,
+    name: web::Path<String>,
+    queues: web::Data<Queues>,
+    libraries: web::Data<ChannelLibraries<L>>,
+) -> impl Responder {
+    match queues.get(name.as_str()) {
+        Some(queue) => {
+            let songs = queue.list().into_iter().map(|item| item.song);
+            m3u(&request, &libraries, &name, songs).await
+        }
+        None => HttpResponse::NotFound().finish(),
+    }
+}
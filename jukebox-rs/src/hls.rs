@@ -0,0 +1,175 @@
+//! HTTP Live Streaming of the channels, as packed MP3 segments.
+//!
+//! Each segment starts with an ID3 tag holding its timestamp and the song
+//! playing, which players expose as timed metadata.
+
+use std::{fmt::Write, time::Duration};
+
+use actix_web::{HttpResponse, Responder, http::header, web};
+use jukebox_channel::{ChannelCommand, Segment, Segments};
+use jukebox_decoder::Format;
+use jukebox_library::{Library, Metadata};
+
+use crate::export::ChannelLibraries;
+
+const PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+const SEGMENT_CONTENT_TYPE: &str = Format::MP3.mime;
+/// Owner of the ID3 frame giving the timestamp of a packed audio segment
+const TIMESTAMP_OWNER: &[u8] = b"com.apple.streaming.transportStreamTimestamp\0";
+/// How often the first segment of a starting channel is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Segments of a channel, `None` if the channel is unknown or without HLS.
+async fn segments(name: &str, channel_manager: &ChannelCommand) -> Option<Segments> {
+    channel_manager.hls(name).await.ok().flatten()
+}
+
+/// Live media playlist of a channel.
+pub(crate) async fn api_playlist<L: Library>(
+    name: web::Path<String>,
+    channel_manager: web::Data<ChannelCommand>,
+    libraries: web::Data<ChannelLibraries<L>>,
+) -> impl Responder {
+    let Some(library) = libraries.get(name.as_str()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(segments) = segments(&name, &channel_manager).await else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    // A channel started by this request has no segment yet
+    let config = segments.config();
+    let mut window = segments.window();
+    let mut waited = Duration::ZERO;
+    while window.segments.is_empty() && waited < config.segment * 2 {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        waited += POLL_INTERVAL;
+        window = segments.window();
+    }
+
+    let target = window
+        .segments
+        .iter()
+        .map(|segment| segment.duration)
+        .chain([config.segment])
+        .max()
+        .unwrap_or_default();
+    let mut playlist = String::new();
+    let _ = writeln!(playlist, "#EXTM3U");
+    let _ = writeln!(playlist, "#EXT-X-VERSION:3");
+    let _ = writeln!(
+        playlist,
+        "#EXT-X-TARGETDURATION:{}",
+        target.as_secs_f64().ceil() as u64
+    );
+    let _ = writeln!(
+        playlist,
+        "#EXT-X-MEDIA-SEQUENCE:{}",
+        window.segments.first().map(|s| s.sequence).unwrap_or(0)
+    );
+    let _ = writeln!(
+        playlist,
+        "#EXT-X-DISCONTINUITY-SEQUENCE:{}",
+        window.discontinuity_sequence
+    );
+    for segment in &window.segments {
+        if segment.discontinuity {
+            let _ = writeln!(playlist, "#EXT-X-DISCONTINUITY");
+        }
+        let title = match segment.track {
+            Some(id) => library
+                .song(id)
+                .await
+                .map(|song| song.title_line())
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        let _ = writeln!(
+            playlist,
+            "#EXTINF:{:.3},{}",
+            segment.duration.as_secs_f64(),
+            title
+        );
+        let _ = writeln!(playlist, "{}.mp3", segment.sequence);
+    }
+
+    HttpResponse::Ok()
+        .content_type(PLAYLIST_CONTENT_TYPE)
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        .body(playlist)
+}
+
+/// A segment of the window, prefixed by its ID3 tag.
+pub(crate) async fn api_segment<L: Library>(
+    path: web::Path<(String, u64)>,
+    channel_manager: web::Data<ChannelCommand>,
+    libraries: web::Data<ChannelLibraries<L>>,
+) -> impl Responder {
+    let (name, sequence) = path.into_inner();
+    let Some(library) = libraries.get(&name) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(segments) = segments(&name, &channel_manager).await else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(segment) = segments.get(sequence) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    let metadata = match segment.track {
+        Some(id) => library.song(id).await.map(|song| song.metadata),
+        None => None,
+    };
+    let tag = id3(&segment, metadata.as_ref());
+    let size: usize = segment.frames.iter().map(|frame| frame.data.len()).sum();
+    let mut body = Vec::with_capacity(tag.len() + size);
+    body.extend_from_slice(&tag);
+    for frame in &segment.frames {
+        body.extend_from_slice(&frame.data);
+    }
+
+    HttpResponse::Ok()
+        .content_type(SEGMENT_CONTENT_TYPE)
+        // Sequence numbers are never reused, even after a restart of the channel
+        .insert_header(header::CacheControl(vec![header::CacheDirective::MaxAge(
+            (segments.config().segment * segments.config().window as u32).as_secs() as u32,
+        )]))
+        .body(body)
+}
+
+/// ID3v2.4 tag with the 90 kHz timestamp of the segment and its song.
+fn id3(segment: &Segment, metadata: Option<&Metadata>) -> Vec<u8> {
+    let timestamp = (segment.start.as_micros() * 9 / 100) as u64 & ((1 << 33) - 1);
+    let mut frames = Vec::new();
+    id3_frame(
+        &mut frames,
+        b"PRIV",
+        &[TIMESTAMP_OWNER, &timestamp.to_be_bytes()].concat(),
+    );
+    if let Some(metadata) = metadata {
+        let texts = [(b"TIT2", &metadata.title), (b"TPE1", &metadata.artist)];
+        for (id, text) in texts {
+            if let Some(text) = text {
+                // UTF-8 encoding
+                id3_frame(&mut frames, id, &[&[3], text.as_bytes()].concat());
+            }
+        }
+    }
+
+    let mut tag = Vec::with_capacity(10 + frames.len());
+    tag.extend_from_slice(b"ID3\x04\x00\x00");
+    tag.extend_from_slice(&synchsafe(frames.len()));
+    tag.extend_from_slice(&frames);
+    tag
+}
+
+fn id3_frame(tag: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    tag.extend_from_slice(id);
+    tag.extend_from_slice(&synchsafe(content.len()));
+    tag.extend_from_slice(&[0, 0]);
+    tag.extend_from_slice(content);
+}
+
+fn synchsafe(size: usize) -> [u8; 4] {
+    [21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7F) as u8)
+}
@@ -0,0 +1,366 @@
+//! Icecast compatibility of the mounts: `icy-*` response headers, in-band
+//! ICY metadata and the `/status-json.xsl` listing read by directories and
+//! monitoring tools.
+
+use std::{
+    pin::Pin,
+    task::{Context, Poll, ready},
+    time::SystemTime,
+};
+
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder, http::header, web};
+use bytes::{Bytes, BytesMut};
+use chrono::{DateTime, Local};
+use futures::{Stream, future::join_all};
+use jukebox_channel::ChannelCommand;
+use serde::Serialize;
+use tokio::sync::watch;
+
+use crate::{
+    config::StationSection,
+    registry::Registry,
+    stream::{Mounts, content_type},
+};
+
+/// Presentation of each channel.
+pub(crate) type Stations = Registry<StationSection>;
+
+/// When the server started.
+pub(crate) struct ServerStart(pub SystemTime);
+
+/// Audio bytes between two metadata blocks.
+pub(crate) const METAINT: usize = 16000;
+
+/// Metadata blocks are at most 255 times 16 bytes.
+const METADATA_SIZE: usize = 255 * 16;
+
+const SERVER: &str = concat!(
+    "jukebox-rs/",
+    env!("CARGO_PKG_VERSION"),
+    " (Icecast 2.4 compatible)"
+);
+
+/// Set the headers Icecast sends to listeners.
+pub(crate) fn headers(
+    builder: &mut HttpResponseBuilder,
+    name: &str,
+    station: &StationSection,
+    bitrate: Option<u32>,
+) {
+    builder
+        .insert_header((header::SERVER, SERVER))
+        .insert_header((header::CACHE_CONTROL, "no-cache, no-store"))
+        .insert_header((header::PRAGMA, "no-cache"))
+        .insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"))
+        .insert_header(("icy-name", station.name.as_deref().unwrap_or(name)))
+        .insert_header(("icy-pub", if station.public { "1" } else { "0" }));
+    let optional = [
+        ("icy-description", &station.description),
+        ("icy-genre", &station.genre),
+        ("icy-url", &station.url),
+    ];
+    for (name, value) in optional {
+        if let Some(value) = value {
+            builder.insert_header((name, value.as_str()));
+        }
+    }
+    if let Some(bitrate) = bitrate {
+        builder
+            .insert_header(("icy-br", bitrate))
+            .insert_header(("ice-audio-info", format!("bitrate={bitrate}")));
+    }
+}
+
+/// Whether the listener asked for in-band metadata.
+pub(crate) fn wants_metadata(request: &HttpRequest) -> bool {
+    request
+        .headers()
+        .get("icy-metadata")
+        .is_some_and(|value| value.as_bytes().trim_ascii() == b"1")
+}
+
+/// Audio with a metadata block every [`METAINT`] bytes, carrying the title
+/// when it changed and empty otherwise.
+pub(crate) struct Metadata<S> {
+    inner: S,
+    title: watch::Receiver<Option<String>>,
+    /// Audio bytes left before the next metadata block
+    remaining: usize,
+}
+
+impl<S> Metadata<S> {
+    pub(crate) fn new(inner: S, mut title: watch::Receiver<Option<String>>) -> Self {
+        // Send the current title in the first block
+        title.mark_changed();
+        Self {
+            inner,
+            title,
+            remaining: METAINT,
+        }
+    }
+
+    fn block(&mut self) -> Vec<u8> {
+        if !self.title.has_changed().unwrap_or(false) {
+            return vec![0];
+        }
+        let mut title = self.title.borrow_and_update().clone().unwrap_or_default();
+        let max = METADATA_SIZE - "StreamTitle='';".len();
+        if title.len() > max {
+            let end = (0..=max).rev().find(|&i| title.is_char_boundary(i));
+            title.truncate(end.unwrap_or(0));
+        }
+        let text = format!("StreamTitle='{title}';");
+
+        let blocks = text.len().div_ceil(16);
+        let mut block = Vec::with_capacity(1 + blocks * 16);
+        block.push(blocks as u8);
+        block.extend_from_slice(text.as_bytes());
+        block.resize(1 + blocks * 16, 0);
+        block
+    }
+}
+
+impl<S, E> Stream for Metadata<S>
+where
+    S: Stream<Item = Result<Bytes, E>> + Unpin,
+{
+    type Item = Result<Bytes, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut data = match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+            Some(Ok(data)) => data,
+            other => return Poll::Ready(other),
+        };
+        if data.len() < this.remaining {
+            this.remaining -= data.len();
+            return Poll::Ready(Some(Ok(data)));
+        }
+
+        let mut output = BytesMut::with_capacity(data.len() + 1);
+        while data.len() >= this.remaining {
+            output.extend_from_slice(&data.split_to(this.remaining));
+            output.extend_from_slice(&this.block());
+            this.remaining = METAINT;
+        }
+        this.remaining -= data.len();
+        output.extend_from_slice(&data);
+        Poll::Ready(Some(Ok(output.freeze())))
+    }
+}
+
+#[derive(Serialize)]
+struct Status {
+    icestats: Icestats,
+}
+
+#[derive(Serialize)]
+struct Icestats {
+    host: String,
+    server_id: &'static str,
+    server_start: String,
+    server_start_iso8601: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<Sources>,
+}
+
+/// Icecast lists a single mount as an object, several ones as an array.
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Sources {
+    One(Box<Source>),
+    Many(Vec<Source>),
+}
+
+#[derive(Serialize)]
+struct Source {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    audio_info: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bitrate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    genre: Option<String>,
+    listener_peak: usize,
+    listeners: usize,
+    listenurl: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server_description: Option<String>,
+    server_name: String,
+    server_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    server_url: Option<String>,
+    stream_start: String,
+    stream_start_iso8601: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+}
+
+/// Dates as Icecast formats them, RFC 2822 and ISO 8601 in local time.
+fn dates(time: SystemTime) -> (String, String) {
+    let time = DateTime::<Local>::from(time);
+    (
+        time.to_rfc2822(),
+        time.format("%Y-%m-%dT%H:%M:%S%z").to_string(),
+    )
+}
+
+/// Host name of a `host[:port]` value.
+fn hostname(host: &str) -> &str {
+    match host.rsplit_once(':') {
+        Some((name, port))
+            if port.bytes().all(|c| c.is_ascii_digit())
+                && (!name.contains(':') || name.ends_with(']')) =>
+        {
+            name
+        }
+        _ => host,
+    }
+}
+
+pub(crate) async fn api_status_json(
+    request: HttpRequest,
+    start: web::Data<ServerStart>,
+    mounts: web::Data<Mounts>,
+    stations: web::Data<Stations>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let connection = request.connection_info().clone();
+    let mut mounts: Vec<(String, String)> = mounts
+        .load()
+        .iter()
+        .map(|(mount, name)| (mount.clone(), name.clone()))
+        .collect();
+    mounts.sort();
+
+    let statuses = join_all(
+        mounts
+            .iter()
+            .map(|(_, name)| channel_manager.status(name.as_str())),
+    )
+    .await;
+    let mut sources: Vec<Source> = mounts
+        .into_iter()
+        .zip(statuses)
+        .filter_map(|((mount, name), status)| {
+            let status = status.ok()?;
+            let station = stations.get(&name).unwrap_or_default();
+            let (stream_start, stream_start_iso8601) = dates(status.started);
+            Some(Source {
+                audio_info: status.bitrate.map(|bitrate| format!("bitrate={bitrate}")),
+                bitrate: status.bitrate,
+                genre: station.genre,
+                listener_peak: status.peak,
+                listeners: status.listeners,
+                listenurl: format!("{}://{}{mount}", connection.scheme(), connection.host()),
+                server_description: station.description,
+                server_name: station.name.unwrap_or(name),
+                server_type: content_type(&status),
+                server_url: station.url,
+                stream_start,
+                stream_start_iso8601,
+                title: status.title,
+            })
+        })
+        .collect();
+
+    let (server_start, server_start_iso8601) = dates(start.0);
+    let source = match sources.len() {
+        0 => None,
+        1 => sources.pop().map(|source| Sources::One(Box::new(source))),
+        _ => Some(Sources::Many(sources)),
+    };
+    HttpResponse::Ok()
+        .insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .json(Status {
+            icestats: Icestats {
+                host: hostname(connection.host()).to_string(),
+                server_id: SERVER,
+                server_start,
+                server_start_iso8601,
+                source,
+            },
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{StreamExt, stream};
+
+    use super::*;
+
+    /// Audio and metadata blocks of an ICY stream, the blocks without their
+    /// length byte and padding.
+    fn parse(mut data: &[u8]) -> (Vec<u8>, Vec<String>) {
+        let (mut audio, mut blocks) = (Vec::new(), Vec::new());
+        loop {
+            let (chunk, rest) = data.split_at(METAINT.min(data.len()));
+            audio.extend_from_slice(chunk);
+            let Some((&length, rest)) = rest.split_first() else {
+                return (audio, blocks);
+            };
+            let (block, rest) = rest.split_at(length as usize * 16);
+            blocks.push(String::from_utf8(block.to_vec()).unwrap());
+            data = rest;
+        }
+    }
+
+    /// Pass `audio` through the metadata inserter by chunks of `size` bytes.
+    async fn insert(audio: &[u8], size: usize, title: watch::Receiver<Option<String>>) -> Vec<u8> {
+        let chunks = audio
+            .chunks(size)
+            .map(|chunk| Ok::<_, ()>(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+        Metadata::new(stream::iter(chunks), title)
+            .map(|data| data.unwrap().to_vec())
+            .concat()
+            .await
+    }
+
+    fn audio(length: usize) -> Vec<u8> {
+        (0..length).map(|i| i as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn block_every_metaint() {
+        let (_sender, title) = watch::channel(Some("Artist - Title".to_string()));
+        let audio = audio(3 * METAINT + 100);
+        // Chunks across the block boundaries, and several blocks in a chunk
+        for size in [1000, 7, 2 * METAINT + 1] {
+            let (played, blocks) = parse(&insert(&audio, size, title.clone()).await);
+            assert_eq!(played, audio);
+            assert_eq!(blocks.len(), 3);
+            assert_eq!(
+                blocks[0].trim_end_matches('\0'),
+                "StreamTitle='Artist - Title';"
+            );
+            // Empty blocks until the title changes
+            assert_eq!(blocks[1], "");
+            assert_eq!(blocks[2], "");
+        }
+    }
+
+    #[test]
+    fn block_on_title_change() {
+        let (sender, title) = watch::channel(None);
+        let mut metadata = Metadata::new(stream::empty::<Result<Bytes, ()>>(), title);
+        assert_eq!(metadata.block(), b"\x01StreamTitle='';\0");
+        assert_eq!(metadata.block(), [0]);
+        sender.send_replace(Some("Next".to_string()));
+        let mut block = b"\x02StreamTitle='Next';".to_vec();
+        block.resize(1 + 2 * 16, 0);
+        assert_eq!(metadata.block(), block);
+        assert_eq!(metadata.block(), [0]);
+    }
+
+    #[test]
+    fn long_title_truncated() {
+        let (_sender, title) = watch::channel(Some("é".repeat(METADATA_SIZE)));
+        let mut metadata = Metadata::new(stream::empty::<Result<Bytes, ()>>(), title);
+        let block = metadata.block();
+        assert_eq!(block[0], 255);
+        assert_eq!(block.len(), 1 + METADATA_SIZE);
+        let text = std::str::from_utf8(&block[1..]).unwrap();
+        assert!(text.trim_end_matches('\0').ends_with("é';"));
+    }
+}
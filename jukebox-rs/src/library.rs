@@ -0,0 +1,322 @@
+use std::{
+    io::{self, SeekFrom},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse, Responder,
+    http::{
+        StatusCode,
+        header::{
+            self, ByteRangeSpec, ContentRangeSpec, EntityTag, HttpDate, IfModifiedSince,
+            IfNoneMatch, IfRange, Range,
+        },
+    },
+    web,
+};
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, stream};
+use jukebox_library::{Library, LibraryId, Resource};
+use serde::Deserialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt};
+use tracing::warn;
+
+use crate::{config::DEFAULT_LIBRARY, registry::Registry};
+
+pub(crate) type Libraries<L> = Registry<L>;
+
+/// Size of the reads of a file being served.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A track of a named library, of the default library when not given.
+#[derive(Deserialize)]
+pub(crate) struct Track {
+    #[serde(default = "default_library")]
+    library: String,
+    id: LibraryId,
+}
+
+fn default_library() -> String {
+    DEFAULT_LIBRARY.to_string()
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Rating {
+    rating: Option<u8>,
+}
+
+pub(crate) async fn api_rate<L: Library>(
+    track: web::Path<Track>,
+    body: web::Json<Rating>,
+    libraries: web::Data<Libraries<L>>,
+) -> impl Responder {
+    if body.rating.is_some_and(|rating| !(1..=5).contains(&rating)) {
+        return HttpResponse::BadRequest().body("rating must be between 1 and 5");
+    }
+    let Some(library) = libraries.get(&track.library) else {
+        return HttpResponse::NotFound().finish();
+    };
+
+    match library.rate(track.id, body.rating).await {
+        true => HttpResponse::NoContent().finish(),
+        false => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Serve the original file of a track, with conditional and range requests.
+pub(crate) async fn api_file<L: Library>(
+    request: HttpRequest,
+    track: web::Path<Track>,
+    libraries: web::Data<Libraries<L>>,
+) -> impl Responder {
+    let Some(library) = libraries.get(&track.library) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(resource) = library.resource(track.id).await else {
+        return HttpResponse::NotFound().finish();
+    };
+    file(&request, resource).await
+}
+
+async fn file(request: &HttpRequest, resource: Resource) -> HttpResponse {
+    let length = resource.length;
+    let etag = etag(&resource);
+    // HTTP dates have a one second precision
+    let modified = resource
+        .modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| HttpDate::from(UNIX_EPOCH + Duration::from_secs(since.as_secs())));
+
+    let unchanged = not_modified(request, etag.as_ref(), modified);
+    let mut builder = if unchanged {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    builder.insert_header((header::ACCEPT_RANGES, "bytes"));
+    if let Some(etag) = &etag {
+        builder.insert_header(header::ETag(etag.clone()));
+    }
+    if let Some(modified) = modified {
+        builder.insert_header(header::LastModified(modified));
+    }
+    if unchanged {
+        return builder.finish();
+    }
+    builder.content_type(resource.format.mime);
+
+    let mut range = (0, length.saturating_sub(1));
+    match requested_range(request, etag.as_ref(), modified) {
+        Some(spec) => match spec.to_satisfiable_range(length) {
+            Some(satisfiable) => {
+                range = satisfiable;
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .insert_header(header::ContentRange(ContentRangeSpec::Bytes {
+                        range: Some(range),
+                        instance_length: Some(length),
+                    }));
+            }
+            None => {
+                return HttpResponse::RangeNotSatisfiable()
+                    .insert_header(header::ContentRange(ContentRangeSpec::Bytes {
+                        range: None,
+                        instance_length: Some(length),
+                    }))
+                    .finish();
+            }
+        },
+        None if length == 0 => return builder.finish(),
+        None => {}
+    }
+
+    let mut body = resource.body;
+    if let Err(e) = body.seek(SeekFrom::Start(range.0)).await {
+        warn!("library: can't seek in track: {}", e);
+        return HttpResponse::InternalServerError().finish();
+    }
+    // The body of HEAD responses is dropped by the server, only its length is sent
+    let size = range.1 - range.0 + 1;
+    builder.no_chunking(size).streaming(chunks(body.take(size)))
+}
+
+/// Read a track by chunks, as it's sent to the client.
+fn chunks(reader: impl AsyncRead + Unpin + 'static) -> impl Stream<Item = io::Result<Bytes>> {
+    stream::try_unfold(reader, |mut reader| async move {
+        let mut chunk = BytesMut::with_capacity(CHUNK_SIZE);
+        match reader.read_buf(&mut chunk).await? {
+            0 => Ok(None),
+            _ => Ok(Some((chunk.freeze(), reader))),
+        }
+    })
+}
+
+/// Strong entity tag of a resource, from its size and modification time.
+///
+/// `None` without modification time, the content isn't read to hash it.
+fn etag(resource: &Resource) -> Option<EntityTag> {
+    let version = resource
+        .modified?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_nanos() as u64;
+    Some(EntityTag::new_strong(format!(
+        "{:x}-{:x}",
+        resource.length, version
+    )))
+}
+
+/// `If-None-Match` takes precedence over `If-Modified-Since`.
+fn not_modified(
+    request: &HttpRequest,
+    etag: Option<&EntityTag>,
+    modified: Option<HttpDate>,
+) -> bool {
+    match request.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => {
+            etag.is_some_and(|etag| tags.iter().any(|tag| tag.weak_eq(etag)))
+        }
+        None => match (request.get_header::<IfModifiedSince>(), modified) {
+            (Some(IfModifiedSince(since)), Some(modified)) => {
+                SystemTime::from(modified) <= SystemTime::from(since)
+            }
+            _ => false,
+        },
+    }
+}
+
+/// Single byte range to serve, multiple ranges are answered with the whole file.
+fn requested_range(
+    request: &HttpRequest,
+    etag: Option<&EntityTag>,
+    modified: Option<HttpDate>,
+) -> Option<ByteRangeSpec> {
+    let fresh = match request.get_header::<IfRange>() {
+        Some(IfRange::EntityTag(tag)) => etag.is_some_and(|etag| tag.strong_eq(etag)),
+        Some(IfRange::Date(date)) => modified == Some(date),
+        None => true,
+    };
+    match request.get_header::<Range>()? {
+        Range::Bytes(mut specs) if fresh && specs.len() == 1 => specs.pop(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body, test::TestRequest};
+    use jukebox_decoder::Format;
+
+    use super::*;
+
+    const DATA: &[u8] = b"0123456789";
+
+    fn modified() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_000_000)
+    }
+
+    /// Serve `DATA` from a file for a request with the given headers.
+    async fn serve(name: &str, headers: &[(header::HeaderName, String)]) -> HttpResponse {
+        let path = std::env::temp_dir().join(format!("jukebox-{}-{name}", std::process::id()));
+        std::fs::write(&path, DATA).unwrap();
+        let resource = Resource {
+            body: Box::new(tokio::fs::File::open(&path).await.unwrap()),
+            length: DATA.len() as u64,
+            modified: Some(modified()),
+            format: Format::MP3,
+        };
+        std::fs::remove_file(&path).unwrap();
+
+        let mut request = TestRequest::get();
+        for (name, value) in headers {
+            request = request.insert_header((name.clone(), value.as_str()));
+        }
+        file(&request.to_http_request(), resource).await
+    }
+
+    fn header(response: &HttpResponse, name: header::HeaderName) -> &str {
+        response.headers().get(name).unwrap().to_str().unwrap()
+    }
+
+    async fn content(response: HttpResponse) -> Bytes {
+        body::to_bytes(response.into_body()).await.unwrap()
+    }
+
+    fn etag() -> String {
+        format!("\"{:x}-{:x}\"", DATA.len(), 1_000_000_000_000_000u64)
+    }
+
+    #[tokio::test]
+    async fn whole_file() {
+        let response = serve("whole", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, header::CONTENT_TYPE), "audio/mpeg");
+        assert_eq!(header(&response, header::CONTENT_LENGTH), "10");
+        assert_eq!(header(&response, header::ETAG), etag());
+        assert_eq!(content(response).await, DATA);
+    }
+
+    #[tokio::test]
+    async fn single_range() {
+        let response = serve("range", &[(header::RANGE, "bytes=2-5".to_string())]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&response, header::CONTENT_RANGE), "bytes 2-5/10");
+        assert_eq!(content(response).await, &DATA[2..=5]);
+
+        let response = serve("suffix", &[(header::RANGE, "bytes=-3".to_string())]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(header(&response, header::CONTENT_RANGE), "bytes 7-9/10");
+        assert_eq!(content(response).await, &DATA[7..]);
+    }
+
+    #[tokio::test]
+    async fn unsatisfiable_range() {
+        let response = serve("unsatisfiable", &[(header::RANGE, "bytes=20-".to_string())]).await;
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(header(&response, header::CONTENT_RANGE), "bytes */10");
+    }
+
+    #[tokio::test]
+    async fn multiple_ranges_serve_whole_file() {
+        let response = serve("multiple", &[(header::RANGE, "bytes=0-1,4-5".to_string())]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(content(response).await, DATA);
+    }
+
+    #[tokio::test]
+    async fn if_range() {
+        let range = (header::RANGE, "bytes=2-5".to_string());
+        let response = serve("if-range", &[range.clone(), (header::IF_RANGE, etag())]).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+        let stale = (header::IF_RANGE, "\"stale\"".to_string());
+        let response = serve("if-range-stale", &[range, stale]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(content(response).await, DATA);
+    }
+
+    #[tokio::test]
+    async fn not_modified() {
+        let response = serve("if-none-match", &[(header::IF_NONE_MATCH, etag())]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(header(&response, header::ETAG), etag());
+
+        let since = HttpDate::from(modified()).to_string();
+        let response = serve("if-modified-since", &[(header::IF_MODIFIED_SINCE, since)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        // If-None-Match takes precedence
+        let since = HttpDate::from(modified()).to_string();
+        let headers = [
+            (header::IF_NONE_MATCH, "\"other\"".to_string()),
+            (header::IF_MODIFIED_SINCE, since),
+        ];
+        let response = serve("if-none-match-other", &headers).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let before = HttpDate::from(modified() - Duration::from_secs(1)).to_string();
+        let response = serve("modified", &[(header::IF_MODIFIED_SINCE, before)]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -1,15 +1,34 @@
 use actix_web::{App, HttpServer, web};
 use clap::Parser as _;
-use std::borrow::Borrow;
+use std::{borrow::Borrow, collections::HashMap, time::SystemTime};
+use tokio::sync::Mutex;
 use tracing::info;
 
-use jukebox_channel::{ChannelCommand, ChannelManager};
+use jukebox_channel::{ChannelCommand, ChannelControl, ChannelManager};
 use jukebox_decoder_mp3::Decoder as Mp3Decoder;
-use jukebox_playlist_random::Playlist as PlaylistRandom;
+use jukebox_library_file::Library as LibraryFile;
 
+mod channel;
 mod cli;
 mod command;
+mod config;
+mod dash;
+mod export;
+mod hls;
+mod icecast;
+mod library;
+mod playlist;
+mod queue;
+mod registry;
+mod reload;
+mod status;
 mod stream;
+mod user;
+mod vote;
+
+use registry::Registry;
+
+type Library = LibraryFile<Mp3Decoder>;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -18,31 +37,196 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .init();
 
     let args = cli::Cli::parse();
+    let config = config::Config::load(&args).unwrap_or_else(|e| {
+        eprintln!("jukebox: {e}");
+        std::process::exit(1)
+    });
+
+    let libraries: HashMap<String, Library> = config
+        .libraries
+        .iter()
+        .map(|(name, section)| Ok((name.clone(), reload::library(name, section)?)))
+        .collect::<Result<_, config::ConfigError>>()
+        .unwrap_or_else(|e| {
+            eprintln!("jukebox: {e}");
+            std::process::exit(1)
+        });
+    let mut channels = HashMap::new();
+    for (name, section) in &config.channels {
+        let channel = channel::Channel::new(name, section, &libraries, None).unwrap_or_else(|e| {
+            eprintln!("jukebox: {e}");
+            std::process::exit(1)
+        });
+        channels.insert(name.clone(), channel);
+    }
 
-    let mut library = jukebox_library_file::Builder::<Mp3Decoder>::new();
-    for path in &args.file_urls {
-        library += path;
+    let mut mounts = HashMap::new();
+    let mut stations = HashMap::new();
+    let mut queues = HashMap::new();
+    let mut ballots = HashMap::new();
+    let mut schedules = HashMap::new();
+    let mut channel_libraries = HashMap::new();
+    let mut library_names = HashMap::new();
+    let mut channel_manager = ChannelManager::new();
+    for (name, channel) in channels {
+        info!("Channel {} on {}", name, channel.mount);
+        mounts.insert(channel.mount, name.clone());
+        stations.insert(name.clone(), config.channels[&name].station.clone());
+        queues.insert(name.clone(), channel.queue);
+        ballots.insert(name.clone(), channel.votes);
+        schedules.insert(name.clone(), channel.schedule);
+        channel_libraries.insert(name.clone(), channel.library);
+        library_names.insert(name.clone(), config.channels[&name].library.clone());
+        let playlist = channel.playlist;
+        channel_manager.create(name, move || playlist.clone(), channel.config);
     }
-    let library = library.build();
-    let playlist = PlaylistRandom::new(library);
-    let mut channel_manager = ChannelManager::new(playlist);
+    let registries = reload::Registries {
+        mounts: web::Data::new(Registry::new(mounts)),
+        stations: web::Data::new(Registry::new(stations)),
+        queues: web::Data::new(Registry::new(queues)),
+        ballots: web::Data::new(Registry::new(ballots)),
+        schedules: web::Data::new(Registry::new(schedules)),
+        libraries: web::Data::new(Registry::new(libraries.clone())),
+        channel_libraries: web::Data::new(export::ChannelLibraries::new(
+            Registry::new(channel_libraries),
+            Registry::new(library_names),
+        )),
+        admin_token: web::Data::new(reload::AdminToken::new(config.server.admin_token.clone())),
+    };
 
     let channel_subscriber: ChannelCommand = channel_manager.borrow().into();
+    let channel_control: ChannelControl<_> = channel_manager.borrow().into();
     tokio::spawn(async move { channel_manager.run().await });
 
-    info!("Starting Jukebox on port {}", args.port);
+    let server_config = config.server.clone();
+    let start = web::Data::new(icecast::ServerStart(SystemTime::now()));
+    let reloader = web::Data::new(Mutex::new(reload::Reloader::new(
+        args,
+        config,
+        libraries,
+        channel_control,
+        registries.clone(),
+    )));
+    #[cfg(unix)]
+    tokio::spawn(reload::on_hangup(reloader.clone().into_inner()));
 
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         let data_channel_manager = web::Data::new(channel_subscriber.clone());
         App::new()
             .app_data(data_channel_manager)
-            .route("/api/stream", web::get().to(stream::api_stream))
+            .app_data(reloader.clone())
+            .app_data(start.clone())
+            .app_data(registries.mounts.clone())
+            .app_data(registries.stations.clone())
+            .app_data(registries.queues.clone())
+            .app_data(registries.ballots.clone())
+            .app_data(registries.schedules.clone())
+            .app_data(registries.libraries.clone())
+            .app_data(registries.channel_libraries.clone())
+            .app_data(registries.admin_token.clone())
+            .route("/api/admin/reload", web::post().to(reload::api_reload))
             .route("/api/next", web::get().to(command::api_next))
             .route("/api/previous", web::get().to(command::api_previous))
-    })
-    .bind(("::", args.port))?
-    .run()
-    .await?;
+            .route(
+                "/api/channels/{name}/next",
+                web::get().to(command::api_next),
+            )
+            .route(
+                "/api/channels/{name}/previous",
+                web::get().to(command::api_previous),
+            )
+            .route(
+                "/api/channels/{name}/status",
+                web::get().to(status::api_status),
+            )
+            .route(
+                "/api/channels/{name}/queue",
+                web::get().to(queue::api_queue),
+            )
+            .route(
+                "/api/channels/{name}/queue",
+                web::post().to(queue::api_enqueue::<Library>),
+            )
+            .route(
+                "/api/channels/{name}/queue/{id}",
+                web::delete().to(queue::api_dequeue),
+            )
+            .route(
+                "/api/admin/channels/{name}/queue/{id}",
+                web::put().to(queue::api_reorder),
+            )
+            .route(
+                "/api/channels/{name}/queue/{id}/vote",
+                web::post().to(vote::api_vote),
+            )
+            .route("/api/channels/{name}/votes", web::get().to(vote::api_votes))
+            .route("/api/channels/{name}/skip", web::post().to(vote::api_skip))
+            .route(
+                "/api/channels/{name}/history.m3u",
+                web::get().to(export::api_history_m3u::<Library>),
+            )
+            .route(
+                "/api/channels/{name}/queue.m3u",
+                web::get().to(export::api_queue_m3u::<Library>),
+            )
+            .route(
+                "/api/library/tracks/{id}/rating",
+                web::put().to(library::api_rate::<Library>),
+            )
+            .route(
+                "/api/libraries/{library}/tracks/{id}/rating",
+                web::put().to(library::api_rate::<Library>),
+            )
+            .route(
+                "/api/library/tracks/{id}/file",
+                web::get().to(library::api_file::<Library>),
+            )
+            .route(
+                "/api/library/tracks/{id}/file",
+                web::head().to(library::api_file::<Library>),
+            )
+            .route(
+                "/api/libraries/{library}/tracks/{id}/file",
+                web::get().to(library::api_file::<Library>),
+            )
+            .route(
+                "/api/libraries/{library}/tracks/{id}/file",
+                web::head().to(library::api_file::<Library>),
+            )
+            .route(
+                "/api/channels/{name}/hls/live.m3u8",
+                web::get().to(hls::api_playlist::<Library>),
+            )
+            .route(
+                "/api/channels/{name}/hls/{sequence}.mp3",
+                web::get().to(hls::api_segment::<Library>),
+            )
+            .route(
+                "/api/channels/{name}/dash/live.mpd",
+                web::get().to(dash::api_manifest),
+            )
+            .route(
+                "/api/channels/{name}/dash/init.mp4",
+                web::get().to(dash::api_init),
+            )
+            .route(
+                "/api/channels/{name}/dash/{sequence}.m4s",
+                web::get().to(dash::api_segment),
+            )
+            .route("/status-json.xsl", web::get().to(icecast::api_status_json))
+            .default_service(web::to(stream::api_stream))
+    });
+    if let Some(max_connections) = server_config.max_connections {
+        server = server.max_connections(max_connections);
+    }
+    if let Some(workers) = server_config.workers {
+        server = server.workers(workers);
+    }
+    for addr in &server_config.bind {
+        info!("Starting Jukebox on {}", addr);
+        server = server.bind(addr)?;
+    }
+    server.run().await?;
 
     Ok(())
 }
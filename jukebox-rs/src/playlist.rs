@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use jukebox_playlist::{DynPlaylist, Playlist};
+use jukebox_playlist_file::Playlist as PlaylistFile;
+use jukebox_playlist_jingle::Playlist as PlaylistJingle;
+use jukebox_playlist_random::Playlist as PlaylistRandom;
+use jukebox_playlist_schedule::Playlist as PlaylistSchedule;
+use jukebox_playlist_sequential::Playlist as PlaylistSequential;
+use jukebox_playlist_shuffle::Playlist as PlaylistShuffle;
+use jukebox_playlist_smart::Playlist as PlaylistSmart;
+use jukebox_playlist_vote::Playlist as PlaylistVote;
+use jukebox_playlist_weighted::{Playlist as PlaylistWeighted, Weights};
+
+use crate::{Library, config::PlaylistSection};
+
+/// Playlist played by a channel: jingles between the voted songs, or the
+/// scheduled program when nothing is queued.
+pub(crate) type ChannelPlaylist =
+    PlaylistJingle<PlaylistVote<PlaylistSchedule<PlaylistKind, PlaylistKind>, Library>, Library>;
+
+/// Playlist selected at runtime by the `type` of a playlist section.
+pub(crate) type PlaylistKind = Box<dyn DynPlaylist>;
+
+pub(crate) fn new(section: &PlaylistSection, library: &Library) -> std::io::Result<PlaylistKind> {
+    let library = library.clone();
+    Ok(match section {
+        PlaylistSection::Random { history } => {
+            let playlist = PlaylistRandom::new(library);
+            match history {
+                Some(history) => playlist.with_history(*history),
+                None => playlist,
+            }
+            .boxed()
+        }
+        PlaylistSection::Shuffle { spacing } => {
+            let playlist = PlaylistShuffle::new(library);
+            match spacing {
+                Some(spacing) => playlist.with_spacing(*spacing),
+                None => playlist,
+            }
+            .boxed()
+        }
+        PlaylistSection::Sequential {
+            order,
+            repeat,
+            filter,
+        } => {
+            let playlist = PlaylistSequential::new(library)
+                .with_order(*order)
+                .with_repeat(*repeat);
+            match filter.clone() {
+                Some(query) => playlist.with_filter(move |song| query.0.matches(song)),
+                None => playlist,
+            }
+            .boxed()
+        }
+        PlaylistSection::Weighted {
+            rating,
+            plays,
+            recency,
+            recency_window,
+        } => {
+            let default = Weights::default();
+            PlaylistWeighted::new(library)
+                .with_weights(Weights {
+                    rating: rating.unwrap_or(default.rating),
+                    plays: plays.unwrap_or(default.plays),
+                    recency: recency.unwrap_or(default.recency),
+                    recency_window: recency_window
+                        .map(Duration::from_secs)
+                        .unwrap_or(default.recency_window),
+                })
+                .boxed()
+        }
+        PlaylistSection::Smart { query } => PlaylistSmart::new(query.0.clone(), library).boxed(),
+        PlaylistSection::File { path, shuffle } => PlaylistFile::open(path, library)?
+            .with_shuffle(*shuffle)
+            .boxed(),
+    })
+}
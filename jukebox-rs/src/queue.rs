@@ -0,0 +1,130 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use jukebox_library::{Library, LibraryId};
+use jukebox_playlist_queue::{Queue, QueueError, QueueId, QueueItem};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    export::ChannelLibraries,
+    registry::Registry,
+    reload::{AdminToken, is_admin},
+    user::user,
+    vote::Ballots,
+};
+
+pub(crate) type Queues = Registry<Queue>;
+
+#[derive(Serialize)]
+struct Item {
+    id: QueueId,
+    song: LibraryId,
+    user: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Enqueue {
+    song: LibraryId,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Reorder {
+    position: usize,
+}
+
+impl From<QueueItem> for Item {
+    fn from(value: QueueItem) -> Self {
+        Self {
+            id: value.id,
+            song: value.song,
+            user: value.user,
+        }
+    }
+}
+
+/// Queued songs in the order they will play, the votes decide it.
+pub(crate) async fn api_queue(
+    name: web::Path<String>,
+    ballots: web::Data<Ballots>,
+) -> impl Responder {
+    match ballots.get(name.as_str()) {
+        Some(votes) => HttpResponse::Ok().json(
+            votes
+                .ranked()
+                .into_iter()
+                .map(|(item, _)| Item::from(item))
+                .collect::<Vec<_>>(),
+        ),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+pub(crate) async fn api_enqueue<L: Library>(
+    request: HttpRequest,
+    name: web::Path<String>,
+    body: web::Json<Enqueue>,
+    queues: web::Data<Queues>,
+    libraries: web::Data<ChannelLibraries<L>>,
+) -> impl Responder {
+    let (Some(queue), Some(library)) = (queues.get(name.as_str()), libraries.get(name.as_str()))
+    else {
+        return HttpResponse::NotFound().finish();
+    };
+    if library.song(body.song).await.is_none() {
+        return HttpResponse::NotFound().body("unknown song");
+    }
+
+    let user = user(&request);
+    match queue.push(body.song, user.clone()) {
+        Ok(id) => HttpResponse::Created().json(Item {
+            id,
+            song: body.song,
+            user,
+        }),
+        Err(e @ QueueError::Limit) => HttpResponse::TooManyRequests().body(e.to_string()),
+    }
+}
+
+/// Remove a song queued by the user, or by anyone for an admin.
+pub(crate) async fn api_dequeue(
+    request: HttpRequest,
+    path: web::Path<(String, QueueId)>,
+    queues: web::Data<Queues>,
+    token: web::Data<AdminToken>,
+) -> impl Responder {
+    let (name, id) = path.into_inner();
+    let Some(queue) = queues.get(&name) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Some(item) = queue.list().into_iter().find(|item| item.id == id) else {
+        return HttpResponse::NotFound().finish();
+    };
+    if item.user != user(&request) && !is_admin(&request, &token) {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    match queue.remove(id) {
+        Some(_) => HttpResponse::NoContent().finish(),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+/// Move a queued song, part of the admin API.
+pub(crate) async fn api_reorder(
+    request: HttpRequest,
+    path: web::Path<(String, QueueId)>,
+    body: web::Json<Reorder>,
+    queues: web::Data<Queues>,
+    token: web::Data<AdminToken>,
+) -> impl Responder {
+    if !is_admin(&request, &token) {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    let (name, id) = path.into_inner();
+    match queues
+        .get(&name)
+        .map(|queue| queue.reorder(id, body.position))
+    {
+        Some(true) => HttpResponse::NoContent().finish(),
+        _ => HttpResponse::NotFound().finish(),
+    }
+}
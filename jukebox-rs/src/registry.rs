@@ -0,0 +1,32 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arc_swap::ArcSwap;
+
+/// Values by channel or library name shared with the API, replaced as a whole
+/// when the configuration is reloaded.
+pub(crate) struct Registry<T> {
+    inner: ArcSwap<HashMap<String, T>>,
+}
+
+impl<T> Registry<T>
+where
+    T: Clone,
+{
+    pub(crate) fn new(values: HashMap<String, T>) -> Self {
+        Self {
+            inner: ArcSwap::from_pointee(values),
+        }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<T> {
+        self.inner.load().get(name).cloned()
+    }
+
+    pub(crate) fn load(&self) -> Arc<HashMap<String, T>> {
+        self.inner.load_full()
+    }
+
+    pub(crate) fn store(&self, values: HashMap<String, T>) {
+        self.inner.store(Arc::new(values));
+    }
+}
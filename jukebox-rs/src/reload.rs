@@ -0,0 +1,333 @@
+//! Apply a new configuration to the running channels, on `SIGHUP` or through
+//! the admin API. Channels whose configuration didn't change keep running
+//! untouched, with their listeners and queue.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error as StdError,
+    fmt::Display,
+    sync::Arc,
+};
+
+use actix_web::{HttpRequest, HttpResponse, Responder, http::header, web};
+use arc_swap::ArcSwapOption;
+use jukebox_channel::{ChannelControl, PlaylistFactory};
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use crate::{
+    Library,
+    channel::Channel,
+    cli::Cli,
+    config::{Config, ConfigError, LibrarySection},
+    export::ChannelLibraries,
+    icecast::Stations,
+    library::Libraries,
+    playlist::ChannelPlaylist,
+    queue::Queues,
+    status::Schedules,
+    stream::Mounts,
+    vote::Ballots,
+};
+
+#[derive(Debug)]
+pub(crate) enum ReloadError {
+    Config(ConfigError),
+    Channel(std::io::Error),
+}
+
+impl StdError for ReloadError {}
+impl Display for ReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReloadError::Config(e) => e.fmt(f),
+            ReloadError::Channel(e) => write!(f, "can't update channels: {e}"),
+        }
+    }
+}
+
+impl From<ConfigError> for ReloadError {
+    fn from(value: ConfigError) -> Self {
+        ReloadError::Config(value)
+    }
+}
+
+impl From<std::io::Error> for ReloadError {
+    fn from(value: std::io::Error) -> Self {
+        ReloadError::Channel(value)
+    }
+}
+
+/// Handles shared with the API.
+#[derive(Clone)]
+pub(crate) struct Registries {
+    pub mounts: web::Data<Mounts>,
+    pub stations: web::Data<Stations>,
+    pub queues: web::Data<Queues>,
+    pub ballots: web::Data<Ballots>,
+    pub schedules: web::Data<Schedules>,
+    pub libraries: web::Data<Libraries<Library>>,
+    pub channel_libraries: web::Data<ChannelLibraries<Library>>,
+    pub admin_token: web::Data<AdminToken>,
+}
+
+/// Bearer token of the admin API, read without waiting for a reload.
+pub(crate) struct AdminToken(ArcSwapOption<String>);
+
+impl AdminToken {
+    pub(crate) fn new(token: Option<String>) -> Self {
+        Self(ArcSwapOption::from(token.map(Arc::new)))
+    }
+
+    fn store(&self, token: Option<String>) {
+        self.0.store(token.map(Arc::new));
+    }
+}
+
+/// Channels touched by a reload.
+#[derive(Debug, Default, Serialize)]
+pub(crate) struct Changes {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+pub(crate) struct Reloader {
+    cli: Cli,
+    config: Config,
+    libraries: HashMap<String, Library>,
+    control: ChannelControl<ChannelPlaylist>,
+    registries: Registries,
+}
+
+/// Scan the paths of a library, which must contain at least one song.
+pub(crate) fn library(name: &str, section: &LibrarySection) -> Result<Library, ConfigError> {
+    let mut library = jukebox_library_file::Builder::new();
+    for path in &section.paths {
+        library += path;
+    }
+    let library = library.build();
+    if library.is_empty() {
+        return Err(ConfigError::EmptyLibrary(name.to_string()));
+    }
+    Ok(library)
+}
+
+impl Reloader {
+    /// Take over the channels created from `config`.
+    pub(crate) fn new(
+        cli: Cli,
+        config: Config,
+        libraries: HashMap<String, Library>,
+        control: ChannelControl<ChannelPlaylist>,
+        registries: Registries,
+    ) -> Self {
+        Self {
+            cli,
+            config,
+            libraries,
+            control,
+            registries,
+        }
+    }
+
+    /// Read the configuration again and update the channels which changed.
+    ///
+    /// Nothing changes when the new configuration is invalid.
+    pub(crate) async fn reload(&mut self) -> Result<Changes, ReloadError> {
+        let config = Config::load(&self.cli)?;
+        if config.server.bind != self.config.server.bind
+            || config.server.max_connections != self.config.server.max_connections
+            || config.server.workers != self.config.server.workers
+        {
+            warn!("reload: server changes need a restart");
+        }
+
+        // Keep unchanged libraries, and their statistics, a rebuilt library
+        // starts without any
+        let mut rebuilt = HashSet::new();
+        let mut libraries = HashMap::new();
+        for (name, section) in &config.libraries {
+            let library = match self.libraries.get(name) {
+                Some(library) if self.config.libraries.get(name) == Some(section) => {
+                    library.clone()
+                }
+                _ => {
+                    rebuilt.insert(name.clone());
+                    // The scan reads the tags of every file
+                    let (name, section) = (name.clone(), section.clone());
+                    tokio::task::spawn_blocking(move || library(&name, &section))
+                        .await
+                        .map_err(std::io::Error::other)??
+                }
+            };
+            libraries.insert(name.clone(), library);
+        }
+
+        let ballots = self.registries.ballots.load();
+        let mut channels = HashMap::new();
+        for (name, section) in &config.channels {
+            let previous = self.config.channels.get(name);
+            let rebuilt_library = std::iter::once(&section.library)
+                .chain(section.jingles.as_ref().map(|jingles| &jingles.library))
+                .any(|library| rebuilt.contains(library));
+            if previous == Some(section) && !rebuilt_library {
+                continue;
+            }
+            // Keep the queue while it refers to the same songs
+            let votes = previous
+                .filter(|previous| {
+                    previous.library == section.library
+                        && previous.queue_limit == section.queue_limit
+                        && previous.skip_threshold == section.skip_threshold
+                        && !rebuilt.contains(&section.library)
+                })
+                .and_then(|_| ballots.get(name).cloned());
+            // Playlist files are read and their entries looked up on the disk
+            let (channel, section, all) = (name.clone(), section.clone(), libraries.clone());
+            let channel =
+                tokio::task::spawn_blocking(move || Channel::new(&channel, &section, &all, votes))
+                    .await
+                    .map_err(std::io::Error::other)??;
+            channels.insert(name.clone(), channel);
+        }
+
+        // The new configuration is valid, apply it
+        let mut changes = Changes::default();
+        let mut create = Vec::new();
+        let mut mounts = HashMap::new();
+        let mut stations = HashMap::new();
+        let mut queues = HashMap::new();
+        let mut schedules = HashMap::new();
+        let mut ballots = HashMap::new();
+        let mut channel_libraries = HashMap::new();
+        let mut library_names = HashMap::new();
+        for (name, section) in &config.channels {
+            let (queue, votes, schedule, library) = match channels.remove(name) {
+                Some(channel) => {
+                    match self.config.channels.contains_key(name) {
+                        true => changes.updated.push(name.clone()),
+                        false => changes.added.push(name.clone()),
+                    }
+                    let playlist = channel.playlist;
+                    let factory: PlaylistFactory<ChannelPlaylist> =
+                        Box::new(move || playlist.clone());
+                    create.push((name.clone(), factory, channel.config));
+                    (
+                        channel.queue,
+                        channel.votes,
+                        channel.schedule,
+                        channel.library,
+                    )
+                }
+                None => (
+                    self.registries.queues.get(name).unwrap(),
+                    self.registries.ballots.get(name).unwrap(),
+                    self.registries.schedules.get(name).unwrap(),
+                    self.registries.channel_libraries.get(name).unwrap(),
+                ),
+            };
+            mounts.insert(section.mount(name), name.clone());
+            stations.insert(name.clone(), section.station.clone());
+            queues.insert(name.clone(), queue);
+            ballots.insert(name.clone(), votes);
+            schedules.insert(name.clone(), schedule);
+            channel_libraries.insert(name.clone(), library);
+            library_names.insert(name.clone(), section.library.clone());
+        }
+        let remove: Vec<String> = self
+            .config
+            .channels
+            .keys()
+            .filter(|name| !config.channels.contains_key(*name))
+            .cloned()
+            .collect();
+        // Every channel changes in one step, or none if the manager stopped
+        self.control.apply(create, remove.clone()).await?;
+        changes.removed = remove;
+
+        self.registries.mounts.store(mounts);
+        self.registries.stations.store(stations);
+        self.registries.queues.store(queues);
+        self.registries.ballots.store(ballots);
+        self.registries.schedules.store(schedules);
+        self.registries.channel_libraries.store(channel_libraries);
+        self.registries.channel_libraries.names.store(library_names);
+        self.registries.libraries.store(libraries.clone());
+        self.registries
+            .admin_token
+            .store(config.server.admin_token.clone());
+
+        self.config = config;
+        self.libraries = libraries;
+        Ok(changes)
+    }
+}
+
+async fn reload(reloader: &Mutex<Reloader>) -> Result<Changes, ReloadError> {
+    let result = reloader.lock().await.reload().await;
+    match &result {
+        Ok(changes) => info!(
+            "reload: added {:?}, updated {:?}, removed {:?}",
+            changes.added, changes.updated, changes.removed
+        ),
+        Err(e) => error!("reload: {e}"),
+    }
+    result
+}
+
+/// Reload the configuration on each `SIGHUP`.
+#[cfg(unix)]
+pub(crate) async fn on_hangup(reloader: Arc<Mutex<Reloader>>) -> std::io::Result<()> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut hangup = signal(SignalKind::hangup())?;
+    while hangup.recv().await.is_some() {
+        let _ = reload(&reloader).await;
+    }
+    Ok(())
+}
+
+/// Compare two tokens without stopping at the first difference, so the
+/// response time doesn't tell how much of the token was guessed.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Whether the request may use the admin API: it gives the admin token, or
+/// comes from a local client when no token is configured.
+pub(crate) fn is_admin(request: &HttpRequest, token: &AdminToken) -> bool {
+    match token.0.load().as_deref() {
+        Some(token) => request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .is_some_and(|v| same_token(v, token)),
+        None => request
+            .peer_addr()
+            .is_some_and(|addr| addr.ip().is_loopback()),
+    }
+}
+
+pub(crate) async fn api_reload(
+    request: HttpRequest,
+    reloader: web::Data<Mutex<Reloader>>,
+    token: web::Data<AdminToken>,
+) -> impl Responder {
+    if !is_admin(&request, &token) {
+        return HttpResponse::Forbidden().finish();
+    }
+
+    match reload(&reloader).await {
+        Ok(changes) => HttpResponse::Ok().json(changes),
+        Err(e @ ReloadError::Config(_)) => HttpResponse::BadRequest().body(e.to_string()),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
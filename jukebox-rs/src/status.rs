@@ -0,0 +1,66 @@
+use actix_web::{HttpResponse, Responder, web};
+use jukebox_channel::ChannelCommand;
+use jukebox_library::LibraryId;
+use jukebox_playlist_schedule::Schedule;
+use serde::Serialize;
+
+use crate::registry::Registry;
+
+pub(crate) type Schedules = Registry<Schedule>;
+
+#[derive(Serialize)]
+struct Program {
+    name: String,
+    rule: String,
+}
+
+#[derive(Serialize)]
+struct ScheduleStatus {
+    active: String,
+    programs: Vec<Program>,
+}
+
+#[derive(Serialize)]
+struct Status {
+    listeners: usize,
+    current: Option<LibraryId>,
+    /// Position in the current song, in seconds
+    position: f64,
+    /// Frames lost by slow listeners
+    dropped: u64,
+    schedule: ScheduleStatus,
+}
+
+impl From<&Schedule> for ScheduleStatus {
+    fn from(value: &Schedule) -> Self {
+        Self {
+            active: value.active(),
+            programs: value
+                .programs()
+                .into_iter()
+                .map(|(name, rule)| Program { name, rule })
+                .collect(),
+        }
+    }
+}
+
+pub(crate) async fn api_status(
+    name: web::Path<String>,
+    schedules: web::Data<Schedules>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(schedule) = schedules.get(name.as_str()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Ok(status) = channel_manager.status(name.as_str()).await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
+    HttpResponse::Ok().json(Status {
+        listeners: status.listeners,
+        current: status.current,
+        position: status.position.as_secs_f64(),
+        dropped: status.dropped,
+        schedule: ScheduleStatus::from(&schedule),
+    })
+}
@@ -1,23 +1,68 @@
-use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder, Responder, http::StatusCode, web};
-use jukebox_channel::{ChannelCommand, Stream};
+use actix_web::{
+    HttpRequest, HttpResponse, HttpResponseBuilder, Responder, http::Method, http::StatusCode, web,
+};
+use jukebox_channel::{ChannelCommand, ChannelStatus};
 
+use crate::{
+    icecast::{self, METAINT, Metadata, Stations},
+    registry::Registry,
+};
+
+/// Channel streamed on each mount path.
+pub(crate) type Mounts = Registry<String>;
+
+/// Mount of the channel controlled by the `/api/next` and `/api/previous` routes.
+pub(crate) const DEFAULT_MOUNT: &str = "/api/stream";
+
+/// Content type of a channel streaming any format.
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Content type of a channel, from the format of its decoder.
+pub(crate) fn content_type(status: &ChannelStatus) -> &'static str {
+    status
+        .format
+        .map_or(DEFAULT_CONTENT_TYPE, |format| format.mime)
+}
+
+/// Stream the channel mounted on the request path, as Icecast would.
 pub(crate) async fn api_stream(
     request: HttpRequest,
+    mounts: web::Data<Mounts>,
+    stations: web::Data<Stations>,
     channel_manager: web::Data<ChannelCommand>,
 ) -> impl Responder {
+    let Some(name) = mounts.get(request.path()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    if request.method() != Method::GET {
+        return HttpResponse::MethodNotAllowed().finish();
+    }
+
+    let Ok(stream) = channel_manager.register(&name).await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+    let Ok(status) = channel_manager.status(&name).await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
     let mut builder = HttpResponseBuilder::new(StatusCode::OK);
-    builder.content_type("audio/mpeg");
-    let stream = Stream::default();
-    channel_manager
-        .register("test", &stream)
-        .await
-        .map(|_| {
-            let mut res = builder.streaming(stream);
-            if request.version() < actix_web::http::Version::HTTP_11 {
-                // Disable chunking transfert encoding for HTTP/1.0
-                res.head_mut().no_chunking(true);
-            }
-            res
-        })
-        .unwrap_or(HttpResponse::InternalServerError().finish())
+    builder.content_type(content_type(&status));
+    let station = stations.get(&name).unwrap_or_default();
+    icecast::headers(&mut builder, &name, &station, status.bitrate);
+    let mut res = match icecast::wants_metadata(&request) {
+        true => {
+            let Ok(title) = channel_manager.title(&name).await else {
+                return HttpResponse::InternalServerError().finish();
+            };
+            builder
+                .insert_header(("icy-metaint", METAINT))
+                .streaming(Metadata::new(stream, title))
+        }
+        false => builder.streaming(stream),
+    };
+    if request.version() < actix_web::http::Version::HTTP_11 {
+        // Disable chunking transfert encoding for HTTP/1.0
+        res.head_mut().no_chunking(true);
+    }
+    res
 }
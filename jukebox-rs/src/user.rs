@@ -0,0 +1,10 @@
+use actix_web::HttpRequest;
+
+/// Identify the user of a request by its address. Headers are set by the
+/// client, they can't be trusted to enforce queue limits or count votes.
+pub(crate) fn user(request: &HttpRequest) -> String {
+    request
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_default()
+}
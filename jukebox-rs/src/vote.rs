@@ -0,0 +1,137 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use jukebox_channel::ChannelCommand;
+use jukebox_library::LibraryId;
+use jukebox_playlist_queue::QueueId;
+use jukebox_playlist_vote::{Skip, Vote, Votes};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{registry::Registry, user::user};
+
+pub(crate) type Ballots = Registry<Votes>;
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum VoteKind {
+    Up,
+    Down,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct Cast {
+    vote: VoteKind,
+}
+
+#[derive(Serialize)]
+struct Score {
+    id: QueueId,
+    song: LibraryId,
+    user: String,
+    score: i64,
+}
+
+#[derive(Serialize)]
+struct ItemScore {
+    id: QueueId,
+    score: i64,
+}
+
+#[derive(Serialize)]
+struct SkipState {
+    votes: usize,
+    required: usize,
+    skipped: bool,
+}
+
+#[derive(Serialize)]
+struct Ballot {
+    queue: Vec<Score>,
+    skip: SkipState,
+}
+
+impl From<VoteKind> for Vote {
+    fn from(value: VoteKind) -> Self {
+        match value {
+            VoteKind::Up => Vote::Up,
+            VoteKind::Down => Vote::Down,
+        }
+    }
+}
+
+impl From<Skip> for SkipState {
+    fn from(value: Skip) -> Self {
+        Self {
+            votes: value.votes,
+            required: value.required,
+            skipped: false,
+        }
+    }
+}
+
+pub(crate) async fn api_votes(
+    name: web::Path<String>,
+    ballots: web::Data<Ballots>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(votes) = ballots.get(name.as_str()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Ok(listeners) = channel_manager.listeners(name.as_str()).await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
+    HttpResponse::Ok().json(Ballot {
+        queue: votes
+            .ranked()
+            .into_iter()
+            .map(|(item, score)| Score {
+                id: item.id,
+                song: item.song,
+                user: item.user,
+                score,
+            })
+            .collect(),
+        skip: votes.skip_state(listeners).into(),
+    })
+}
+
+pub(crate) async fn api_vote(
+    request: HttpRequest,
+    path: web::Path<(String, QueueId)>,
+    body: web::Json<Cast>,
+    ballots: web::Data<Ballots>,
+) -> impl Responder {
+    let (name, id) = path.into_inner();
+    match ballots
+        .get(&name)
+        .and_then(|votes| votes.vote(id, user(&request), body.vote.into()))
+    {
+        Some(score) => HttpResponse::Ok().json(ItemScore { id, score }),
+        None => HttpResponse::NotFound().finish(),
+    }
+}
+
+pub(crate) async fn api_skip(
+    request: HttpRequest,
+    name: web::Path<String>,
+    ballots: web::Data<Ballots>,
+    channel_manager: web::Data<ChannelCommand>,
+) -> impl Responder {
+    let Some(votes) = ballots.get(name.as_str()) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let Ok(listeners) = channel_manager.listeners(name.as_str()).await else {
+        return HttpResponse::InternalServerError().finish();
+    };
+
+    let skip = votes.skip(user(&request), listeners);
+    let mut state = SkipState::from(skip);
+    if skip.reached() {
+        info!("vote: skip {} with {} votes", name, skip.votes);
+        if channel_manager.next(name.as_str()).await.is_err() {
+            return HttpResponse::InternalServerError().finish();
+        }
+        state.skipped = true;
+    }
+    HttpResponse::Ok().json(state)
+}